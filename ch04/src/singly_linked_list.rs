@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::rc::Rc;
 
 type SingleLink = Option<Rc<RefCell<Node>>>;
@@ -59,6 +60,102 @@ impl TransactionLog {
                 .value
         })
     }
+
+    // A stable bottom-up merge sort: runs of `width` nodes are merged pairwise,
+    // doubling the width until a single run remains
+    pub fn sort_by<F: Fn(&str, &str) -> Ordering>(&mut self, cmp: F) {
+        let mut width = 1;
+        loop {
+            let mut rest = self.head.take();
+            let mut head: SingleLink = None;
+            let mut tail: SingleLink = None;
+            let mut merges = 0;
+
+            while rest.is_some() {
+                let left = rest;
+                let right = TransactionLog::split_after(&left, width);
+                rest = TransactionLog::split_after(&right, width);
+
+                let (run_head, run_tail) = TransactionLog::merge(left, right, &cmp);
+                match tail.take() {
+                    Some(t) => t.borrow_mut().next = run_head,
+                    None => head = run_head,
+                }
+                tail = run_tail;
+                merges += 1;
+            }
+
+            self.head = head;
+            self.tail = tail;
+            if merges <= 1 {
+                break;
+            }
+            width *= 2;
+        }
+    }
+
+    // Cuts the list after `n` nodes and returns the remainder
+    fn split_after(head: &SingleLink, n: usize) -> SingleLink {
+        let mut current = head.clone();
+        for _ in 1..n {
+            current = current.and_then(|node| node.borrow().next.clone());
+        }
+        current.and_then(|node| node.borrow_mut().next.take())
+    }
+
+    fn merge<F: Fn(&str, &str) -> Ordering>(
+        a: SingleLink,
+        b: SingleLink,
+        cmp: &F,
+    ) -> (SingleLink, SingleLink) {
+        let (mut a, mut b) = (a, b);
+        let mut head: SingleLink = None;
+        let mut tail: SingleLink = None;
+
+        while a.is_some() || b.is_some() {
+            // on ties the left run goes first, which keeps the sort stable
+            let take_a = match (&a, &b) {
+                (Some(x), Some(y)) => {
+                    cmp(&x.borrow().value, &y.borrow().value) != Ordering::Greater
+                }
+                (Some(_), None) => true,
+                _ => false,
+            };
+            let source = if take_a { &mut a } else { &mut b };
+            let node = source.take().unwrap();
+            *source = node.borrow_mut().next.take();
+
+            match tail.take() {
+                Some(t) => t.borrow_mut().next = Some(node.clone()),
+                None => head = Some(node.clone()),
+            }
+            tail = Some(node);
+        }
+        (head, tail)
+    }
+
+    // Entries are joined with '\n', so they must not contain newlines themselves.
+    // A log holding a single empty entry also serializes to "" and comes back empty.
+    pub fn serialize(&self) -> String {
+        let mut entries = vec![];
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            let node = node.borrow();
+            entries.push(node.value.clone());
+            current = node.next.clone();
+        }
+        entries.join("\n")
+    }
+
+    pub fn deserialize(data: &str) -> TransactionLog {
+        let mut log = TransactionLog::new_empty();
+        if !data.is_empty() {
+            for entry in data.split('\n') {
+                log.append(entry.to_owned());
+            }
+        }
+        log
+    }
 }
 
 #[cfg(test)]
@@ -109,4 +206,59 @@ mod tests {
         );
         assert_eq!(list.pop(), None);
     }
+
+    #[test]
+    fn transaction_log_serialize() {
+        let mut list = TransactionLog::new_empty();
+        assert_eq!(list.serialize(), "");
+        assert_eq!(TransactionLog::deserialize("").length, 0);
+
+        let mut single_empty = TransactionLog::new_empty();
+        single_empty.append("".to_owned());
+        assert_eq!(single_empty.serialize(), "");
+        assert_eq!(
+            TransactionLog::deserialize(&single_empty.serialize()).length,
+            0
+        );
+
+        list.append("INSERT INTO mytable VALUES (1,2,3)".to_owned());
+        list.append("INSERT INTO mytable VALUES (2,3,4)".to_owned());
+        list.append("".to_owned());
+        list.append("INSERT INTO mytable VALUES (3,4,5)".to_owned());
+
+        let data = list.serialize();
+        let mut copy = TransactionLog::deserialize(&data);
+        assert_eq!(copy.length, 4);
+        assert_eq!(copy.serialize(), data);
+        while let Some(entry) = list.pop() {
+            assert_eq!(copy.pop(), Some(entry));
+        }
+        assert_eq!(copy.pop(), None);
+    }
+
+    #[test]
+    fn transaction_log_sort_by() {
+        let mut list = TransactionLog::new_empty();
+        list.sort_by(|a, b| a.len().cmp(&b.len()));
+        assert_eq!(list.pop(), None);
+
+        let entries = vec!["ccc", "a", "bb", "dd", "e", "ffff", "ggg", "h", "ii"];
+        for entry in entries.iter() {
+            list.append(entry.to_string());
+        }
+        list.sort_by(|a, b| a.len().cmp(&b.len()));
+        assert_eq!(list.length, 9);
+
+        // the tail has to be valid after sorting as well
+        list.append("j".to_owned());
+
+        let mut sorted = vec![];
+        while let Some(entry) = list.pop() {
+            sorted.push(entry);
+        }
+        assert_eq!(
+            sorted,
+            vec!["a", "e", "h", "bb", "dd", "ii", "ccc", "ggg", "ffff", "j"]
+        );
+    }
 }
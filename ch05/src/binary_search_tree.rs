@@ -1,3 +1,6 @@
+use std::cmp;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::mem;
 
 #[derive(Clone, Debug)]
@@ -53,16 +56,33 @@ impl DeviceRegistry {
         }
     }
 
+    // Expects `devices` sorted by id and builds a height-balanced tree from them
+    pub fn from_sorted(devices: &[IoTDevice]) -> DeviceRegistry {
+        let mut registry = DeviceRegistry::new_empty();
+        registry.add_balanced(devices);
+        registry
+    }
+
     pub fn add(&mut self, device: IoTDevice) {
         self.length += 1;
         let root = mem::replace(&mut self.root, None);
         self.root = self.add_rec(root, device);
     }
 
+    // Leaves the tree as it is if the id is taken already
+    pub fn add_unique(&mut self, device: IoTDevice) -> bool {
+        if self.contains(device.numerical_id) {
+            false
+        } else {
+            self.add(device);
+            true
+        }
+    }
+
     fn add_rec(&mut self, node: Tree, device: IoTDevice) -> Tree {
         match node {
             Some(mut n) => {
-                if n.dev.numerical_id <= device.numerical_id {
+                if device.numerical_id < n.dev.numerical_id {
                     n.left = self.add_rec(n.left, device);
                 } else {
                     n.right = self.add_rec(n.right, device);
@@ -82,7 +102,7 @@ impl DeviceRegistry {
             Some(n) => {
                 if n.dev.numerical_id == numerical_id {
                     Some(n.dev.clone())
-                } else if n.dev.numerical_id < numerical_id {
+                } else if numerical_id < n.dev.numerical_id {
                     self.find_r(&n.left, numerical_id)
                 } else {
                     self.find_r(&n.right, numerical_id)
@@ -92,10 +112,305 @@ impl DeviceRegistry {
         }
     }
 
+    pub fn count_leaves(&self) -> usize {
+        self.count_leaves_r(&self.root)
+    }
+
+    fn count_leaves_r(&self, node: &Tree) -> usize {
+        match node {
+            Some(n) if n.left.is_none() && n.right.is_none() => 1,
+            Some(n) => self.count_leaves_r(&n.left) + self.count_leaves_r(&n.right),
+            _ => 0,
+        }
+    }
+
+    pub fn subtree_size(&self, numerical_id: u64) -> Option<usize> {
+        let node = self.get_node(numerical_id)?;
+        Some(self.size_r(&node.left) + 1 + self.size_r(&node.right))
+    }
+
+    fn size_r(&self, node: &Tree) -> usize {
+        match node {
+            Some(n) => self.size_r(&n.left) + 1 + self.size_r(&n.right),
+            _ => 0,
+        }
+    }
+
+    fn get_node(&self, numerical_id: u64) -> Option<&Node> {
+        let mut node = &self.root;
+        while let Some(n) = node {
+            if n.dev.numerical_id == numerical_id {
+                return Some(n);
+            }
+            node = if numerical_id < n.dev.numerical_id {
+                &n.left
+            } else {
+                &n.right
+            };
+        }
+        None
+    }
+
+    pub fn contains(&self, numerical_id: u64) -> bool {
+        self.get_node(numerical_id).is_some()
+    }
+
+    pub fn remove(&mut self, numerical_id: u64) -> Option<IoTDevice> {
+        let removed = DeviceRegistry::remove_r(&mut self.root, numerical_id);
+        if removed.is_some() {
+            self.length -= 1;
+        }
+        removed
+    }
+
+    fn remove_r(node: &mut Tree, numerical_id: u64) -> Option<IoTDevice> {
+        let n = node.as_mut()?;
+        if n.dev.numerical_id == numerical_id {
+            let mut n = node.take().unwrap();
+            *node = match (n.left.take(), n.right.take()) {
+                (None, None) => None,
+                (Some(child), None) | (None, Some(child)) => Some(child),
+                // the in-order successor is the first node of the right subtree
+                (left, right) => {
+                    let mut right = right;
+                    let mut successor = DeviceRegistry::take_leftmost(&mut right);
+                    successor.left = left;
+                    successor.right = right;
+                    Some(successor)
+                }
+            };
+            Some(n.dev)
+        } else if numerical_id < n.dev.numerical_id {
+            DeviceRegistry::remove_r(&mut n.left, numerical_id)
+        } else {
+            DeviceRegistry::remove_r(&mut n.right, numerical_id)
+        }
+    }
+
+    // Unlinks the leftmost node below `node`, moving its right subtree up in its place
+    fn take_leftmost(node: &mut Tree) -> Box<Node> {
+        if node.as_ref().unwrap().left.is_some() {
+            DeviceRegistry::take_leftmost(&mut node.as_mut().unwrap().left)
+        } else {
+            let mut n = node.take().unwrap();
+            *node = n.right.take();
+            n
+        }
+    }
+
+    pub fn min(&self) -> Option<IoTDevice> {
+        let mut n = self.root.as_ref()?;
+        while let Some(ref left) = n.left {
+            n = left;
+        }
+        Some(n.dev.clone())
+    }
+
+    pub fn max(&self) -> Option<IoTDevice> {
+        let mut n = self.root.as_ref()?;
+        while let Some(ref right) = n.right {
+            n = right;
+        }
+        Some(n.dev.clone())
+    }
+
+    // The device with the next larger id, whether or not `numerical_id` is stored
+    pub fn successor(&self, numerical_id: u64) -> Option<IoTDevice> {
+        let mut node = &self.root;
+        let mut candidate = None;
+        while let Some(n) = node {
+            // the last node we turned left at is the closest larger id so far
+            node = if numerical_id < n.dev.numerical_id {
+                candidate = Some(n);
+                &n.left
+            } else {
+                &n.right
+            };
+        }
+        candidate.map(|n| n.dev.clone())
+    }
+
+    // Number of nodes on the longest path from the root down to a leaf
+    pub fn height(&self) -> usize {
+        self.height_r(&self.root)
+    }
+
+    // True if the heights of the two subtrees differ by at most one at every node
+    pub fn is_balanced(&self) -> bool {
+        self.balanced_height(&self.root).is_some()
+    }
+
+    fn balanced_height(&self, node: &Tree) -> Option<usize> {
+        match node {
+            Some(n) => {
+                let left = self.balanced_height(&n.left)?;
+                let right = self.balanced_height(&n.right)?;
+                if cmp::max(left, right) - cmp::min(left, right) > 1 {
+                    None
+                } else {
+                    Some(1 + cmp::max(left, right))
+                }
+            }
+            _ => Some(0),
+        }
+    }
+
+    fn height_r(&self, node: &Tree) -> usize {
+        match node {
+            Some(n) => 1 + cmp::max(self.height_r(&n.left), self.height_r(&n.right)),
+            _ => 0,
+        }
+    }
+
+    pub fn union(&self, other: &DeviceRegistry) -> DeviceRegistry {
+        self.merge_sets(other, true, true, true)
+    }
+
+    pub fn intersection(&self, other: &DeviceRegistry) -> DeviceRegistry {
+        self.merge_sets(other, false, true, false)
+    }
+
+    pub fn difference(&self, other: &DeviceRegistry) -> DeviceRegistry {
+        self.merge_sets(other, true, false, false)
+    }
+
+    // Walks both id streams in ascending order and keeps an id depending on
+    // which side(s) it was found in. Ids present in both keep self's device.
+    fn merge_sets(
+        &self,
+        other: &DeviceRegistry,
+        only_self: bool,
+        both: bool,
+        only_other: bool,
+    ) -> DeviceRegistry {
+        let mut a = self.sorted_devices().into_iter().peekable();
+        let mut b = other.sorted_devices().into_iter().peekable();
+        let mut devices = vec![];
+
+        loop {
+            let order = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) => x.numerical_id.cmp(&y.numerical_id),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            let (dev, keep) = match order {
+                Ordering::Less => (a.next().unwrap(), only_self),
+                Ordering::Greater => (b.next().unwrap(), only_other),
+                Ordering::Equal => {
+                    b.next();
+                    (a.next().unwrap(), both)
+                }
+            };
+            if keep {
+                devices.push(dev);
+            }
+        }
+
+        let mut result = DeviceRegistry::new_empty();
+        result.add_balanced(&devices);
+        result
+    }
+
+    // Rebuilds the tree from its sorted devices, inserting the middle one first
+    pub fn rebalance(&mut self) {
+        let mut devices = vec![];
+        self.collect_sorted(&self.root, &mut devices);
+        self.root = None;
+        self.length = 0;
+        self.add_balanced(&devices);
+    }
+
+    fn add_balanced(&mut self, devices: &[IoTDevice]) {
+        if !devices.is_empty() {
+            let mid = devices.len() / 2;
+            self.add(devices[mid].clone());
+            self.add_balanced(&devices[..mid]);
+            self.add_balanced(&devices[mid + 1..]);
+        }
+    }
+
+    // Ascending by id, with repeated ids collapsed into the first one found
+    fn sorted_devices(&self) -> Vec<IoTDevice> {
+        let mut devices = vec![];
+        self.collect_sorted(&self.root, &mut devices);
+        devices.dedup_by_key(|d| d.numerical_id);
+        devices
+    }
+
+    fn collect_sorted(&self, node: &Tree, devices: &mut Vec<IoTDevice>) {
+        if let Some(n) = node {
+            self.collect_sorted(&n.left, devices);
+            devices.push(n.dev.clone());
+            self.collect_sorted(&n.right, devices);
+        }
+    }
+
+    pub fn for_each_range(&self, low: u64, high: u64, mut callback: impl FnMut(&IoTDevice)) {
+        self.for_each_range_r(&self.root, low, high, &mut callback);
+    }
+
+    fn for_each_range_r(
+        &self,
+        node: &Tree,
+        low: u64,
+        high: u64,
+        callback: &mut impl FnMut(&IoTDevice),
+    ) {
+        if let Some(n) = node {
+            let id = n.dev.numerical_id;
+            // repeated ids are kept on the right
+            if low < id {
+                self.for_each_range_r(&n.left, low, high, callback);
+            }
+            if low <= id && id <= high {
+                callback(&n.dev);
+            }
+            if id <= high {
+                self.for_each_range_r(&n.right, low, high, callback);
+            }
+        }
+    }
+
     pub fn walk(&self, callback: impl Fn(&IoTDevice) -> ()) {
         self.walk_in_order(&self.root, &callback);
     }
 
+    pub fn iter(&self) -> DeviceRegistryIter<'_> {
+        let mut iter = DeviceRegistryIter { stack: vec![] };
+        iter.descend(&self.root);
+        iter
+    }
+
+    pub fn walk_reverse(&self, callback: impl Fn(&IoTDevice)) {
+        self.walk_in_reverse_order(&self.root, &callback);
+    }
+
+    fn walk_in_reverse_order(&self, node: &Tree, callback: &impl Fn(&IoTDevice)) {
+        if let Some(n) = node {
+            self.walk_in_reverse_order(&n.right, callback);
+            callback(&n.dev);
+            self.walk_in_reverse_order(&n.left, callback);
+        }
+    }
+
+    // Breadth-first, passing each device along with its depth (the root is at 0)
+    pub fn walk_level_order(&self, callback: impl Fn(&IoTDevice, usize)) {
+        let mut queue = VecDeque::new();
+        if let Some(ref root) = self.root {
+            queue.push_back((root, 0));
+        }
+        while let Some((n, depth)) = queue.pop_front() {
+            callback(&n.dev, depth);
+            if let Some(ref left) = n.left {
+                queue.push_back((left, depth + 1));
+            }
+            if let Some(ref right) = n.right {
+                queue.push_back((right, depth + 1));
+            }
+        }
+    }
+
     fn walk_in_order(&self, node: &Tree, callback: &impl Fn(&IoTDevice) -> ()) {
         if let Some(n) = node {
             self.walk_in_order(&n.left, callback);
@@ -105,6 +420,40 @@ impl DeviceRegistry {
     }
 }
 
+impl<'a> IntoIterator for &'a DeviceRegistry {
+    type Item = &'a IoTDevice;
+    type IntoIter = DeviceRegistryIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct DeviceRegistryIter<'a> {
+    // nodes whose device is yet to come, the next one on top
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> DeviceRegistryIter<'a> {
+    fn descend(&mut self, node: &'a Tree) {
+        let mut node = node;
+        while let Some(n) = node {
+            self.stack.push(n);
+            node = &n.left;
+        }
+    }
+}
+
+impl<'a> Iterator for DeviceRegistryIter<'a> {
+    type Item = &'a IoTDevice;
+
+    fn next(&mut self) -> Option<&'a IoTDevice> {
+        let n = self.stack.pop()?;
+        self.descend(&n.right);
+        Some(&n.dev)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -174,8 +523,416 @@ mod tests {
         let v: RefCell<Vec<IoTDevice>> = RefCell::new(vec![]);
         tree.walk(|n| v.borrow_mut().push(n.clone()));
         let mut items = items;
-        // sort in descending order:
-        items.sort_by(|a, b| b.numerical_id.cmp(&a.numerical_id));
+        // sort in ascending order:
+        items.sort_by_key(|d| d.numerical_id);
         assert_eq!(v.into_inner(), items)
     }
+
+    #[test]
+    fn binary_search_tree_set_operations() {
+        let mut rng = thread_rng();
+        let mut a_items: Vec<IoTDevice> = (0..10).map(new_device_with_id).collect();
+        let mut b_items: Vec<IoTDevice> = (5..15).map(new_device_with_id).collect();
+        rng.shuffle(&mut a_items);
+        rng.shuffle(&mut b_items);
+
+        let mut a = DeviceRegistry::new_empty();
+        let mut b = DeviceRegistry::new_empty();
+        for item in a_items {
+            a.add(item);
+        }
+        for item in b_items {
+            b.add(item);
+        }
+
+        let union = a.union(&b);
+        assert_eq!(union.length, 15);
+        for id in 0..15 {
+            assert_eq!(union.find(id), Some(new_device_with_id(id)));
+        }
+
+        let intersection = a.intersection(&b);
+        assert_eq!(intersection.length, 5);
+        for id in 0..15 {
+            assert_eq!(intersection.find(id).is_some(), (5..10).contains(&id));
+        }
+
+        let difference = a.difference(&b);
+        assert_eq!(difference.length, 5);
+        for id in 0..15 {
+            assert_eq!(difference.find(id).is_some(), id < 5);
+        }
+
+        let empty = DeviceRegistry::new_empty();
+        assert_eq!(a.union(&empty).length, 10);
+        assert_eq!(a.intersection(&empty).length, 0);
+        assert_eq!(empty.difference(&a).length, 0);
+    }
+
+    #[test]
+    fn binary_search_tree_for_each_range() {
+        let mut tree = DeviceRegistry::new_empty();
+        let mut items: Vec<IoTDevice> = (0..20).map(new_device_with_id).collect();
+
+        let mut rng = thread_rng();
+        rng.shuffle(&mut items);
+        for item in items.iter() {
+            tree.add(item.clone());
+        }
+
+        let mut visited = vec![];
+        tree.for_each_range(5, 12, |d| visited.push(d.numerical_id));
+        assert_eq!(visited, (5..=12).collect::<Vec<u64>>());
+
+        let mut visited = vec![];
+        tree.for_each_range(18, 100, |d| visited.push(d.numerical_id));
+        assert_eq!(visited, vec![18, 19]);
+
+        let mut visited = vec![];
+        tree.for_each_range(12, 5, |d| visited.push(d.numerical_id));
+        assert!(visited.is_empty());
+    }
+
+    #[test]
+    fn binary_search_tree_remove() {
+        let mut tree = DeviceRegistry::new_empty();
+        assert_eq!(tree.remove(1), None);
+
+        for id in [4, 2, 6, 1, 3, 5, 7] {
+            tree.add(new_device_with_id(id));
+        }
+        let ids = |tree: &DeviceRegistry| {
+            let v: RefCell<Vec<u64>> = RefCell::new(vec![]);
+            tree.walk(|n| v.borrow_mut().push(n.numerical_id));
+            v.into_inner()
+        };
+
+        // the root, with two children
+        assert_eq!(tree.remove(4), Some(new_device_with_id(4)));
+        assert_eq!(tree.length, 6);
+        assert_eq!(ids(&tree), vec![1, 2, 3, 5, 6, 7]);
+
+        // a leaf
+        assert_eq!(tree.remove(7), Some(new_device_with_id(7)));
+        assert_eq!(tree.length, 5);
+        assert_eq!(ids(&tree), vec![1, 2, 3, 5, 6]);
+
+        // an inner node with two children
+        assert_eq!(tree.remove(2), Some(new_device_with_id(2)));
+        assert_eq!(tree.length, 4);
+        assert_eq!(ids(&tree), vec![1, 3, 5, 6]);
+
+        assert_eq!(tree.remove(2), None);
+        assert_eq!(tree.remove(100), None);
+        assert_eq!(tree.length, 4);
+
+        for id in [6, 1, 5, 3] {
+            assert_eq!(tree.remove(id), Some(new_device_with_id(id)));
+            assert_eq!(tree.find(id), None);
+        }
+        assert_eq!(tree.length, 0);
+        assert!(ids(&tree).is_empty());
+    }
+
+    #[test]
+    fn binary_search_tree_remove_random() {
+        let len = 100;
+        let mut tree = DeviceRegistry::new_empty();
+        let mut items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut rng = thread_rng();
+        rng.shuffle(&mut items);
+        for item in items.iter() {
+            tree.add(item.clone());
+        }
+
+        rng.shuffle(&mut items);
+        let (removed, kept) = items.split_at(len as usize / 2);
+        for item in removed {
+            assert_eq!(tree.remove(item.numerical_id), Some(item.clone()));
+        }
+        assert_eq!(tree.length, len / 2);
+        for item in removed {
+            assert_eq!(tree.find(item.numerical_id), None);
+        }
+        for item in kept {
+            assert_eq!(tree.find(item.numerical_id), Some(item.clone()));
+        }
+
+        let v: RefCell<Vec<u64>> = RefCell::new(vec![]);
+        tree.walk(|n| v.borrow_mut().push(n.numerical_id));
+        let mut expected: Vec<u64> = kept.iter().map(|d| d.numerical_id).collect();
+        expected.sort();
+        assert_eq!(v.into_inner(), expected);
+    }
+
+    #[test]
+    fn binary_search_tree_min_max_height() {
+        let mut tree = DeviceRegistry::new_empty();
+        assert_eq!(tree.min(), None);
+        assert_eq!(tree.max(), None);
+        assert_eq!(tree.height(), 0);
+
+        tree.add(new_device_with_id(5));
+        assert_eq!(tree.min(), Some(new_device_with_id(5)));
+        assert_eq!(tree.max(), Some(new_device_with_id(5)));
+        assert_eq!(tree.height(), 1);
+
+        // ascending inserts degrade into a chain to the right
+        for id in 6..15 {
+            tree.add(new_device_with_id(id));
+        }
+        assert_eq!(tree.height(), 10);
+        assert_eq!(tree.min(), Some(new_device_with_id(5)));
+        assert_eq!(tree.max(), Some(new_device_with_id(14)));
+
+        tree.add(new_device_with_id(3));
+        tree.add(new_device_with_id(4));
+        assert_eq!(tree.height(), 10);
+        assert_eq!(tree.min(), Some(new_device_with_id(3)));
+
+        for id in (0..3).rev() {
+            tree.add(new_device_with_id(id));
+        }
+        assert_eq!(tree.height(), 10);
+        assert_eq!(tree.min(), Some(new_device_with_id(0)));
+        assert_eq!(tree.max(), Some(new_device_with_id(14)));
+    }
+
+    #[test]
+    fn binary_search_tree_subtree_size() {
+        let mut tree = DeviceRegistry::new_empty();
+        assert_eq!(tree.subtree_size(4), None);
+
+        //        4
+        //      /   \
+        //     2     6
+        //    / \     \
+        //   1   3     8
+        //            /
+        //           7
+        for id in [4, 2, 6, 1, 3, 8, 7] {
+            tree.add(new_device_with_id(id));
+        }
+        assert_eq!(tree.subtree_size(4), Some(tree.length as usize));
+        assert_eq!(tree.subtree_size(2), Some(3));
+        assert_eq!(tree.subtree_size(6), Some(3));
+        assert_eq!(tree.subtree_size(8), Some(2));
+        for leaf in [1, 3, 7] {
+            assert_eq!(tree.subtree_size(leaf), Some(1));
+        }
+        assert_eq!(tree.subtree_size(5), None);
+    }
+
+    #[test]
+    fn binary_search_tree_rebalance() {
+        let len = 1000;
+        let mut tree = DeviceRegistry::new_empty();
+        tree.rebalance();
+        assert_eq!(tree.length, 0);
+        assert_eq!(tree.height(), 0);
+
+        for id in 0..len {
+            tree.add(new_device_with_id(id));
+        }
+        tree.add(new_device_with_id(500));
+        assert_eq!(tree.height(), len as usize);
+
+        tree.rebalance();
+        assert_eq!(tree.length, len + 1);
+        // ceil(log2(1001)) = 10
+        assert!(tree.height() <= 10);
+        for id in 0..len {
+            assert_eq!(tree.find(id), Some(new_device_with_id(id)));
+        }
+        let mut visited = 0;
+        tree.for_each_range(500, 500, |_| visited += 1);
+        assert_eq!(visited, 2);
+    }
+
+    #[test]
+    fn binary_search_tree_walk_level_order() {
+        let mut tree = DeviceRegistry::new_empty();
+        let v: RefCell<Vec<(u64, usize)>> = RefCell::new(vec![]);
+        tree.walk_level_order(|n, depth| v.borrow_mut().push((n.numerical_id, depth)));
+        assert!(v.borrow().is_empty());
+
+        tree.add(new_device_with_id(4));
+        tree.walk_level_order(|n, depth| v.borrow_mut().push((n.numerical_id, depth)));
+        assert_eq!(v.replace(vec![]), vec![(4, 0)]);
+
+        for id in [2, 6, 1, 3, 8, 7] {
+            tree.add(new_device_with_id(id));
+        }
+        tree.walk_level_order(|n, depth| v.borrow_mut().push((n.numerical_id, depth)));
+        assert_eq!(
+            v.into_inner(),
+            vec![(4, 0), (2, 1), (6, 1), (1, 2), (3, 2), (8, 2), (7, 3)]
+        );
+    }
+
+    #[test]
+    fn binary_search_tree_iter() {
+        let mut tree = DeviceRegistry::new_empty();
+        assert_eq!(tree.iter().next(), None);
+
+        let mut items: Vec<IoTDevice> = (0..50).map(new_device_with_id).collect();
+        let mut rng = thread_rng();
+        rng.shuffle(&mut items);
+        for item in items.iter() {
+            tree.add(item.clone());
+        }
+
+        let actual: Vec<IoTDevice> = tree.iter().cloned().collect();
+        items.sort_by_key(|d| d.numerical_id);
+        assert_eq!(actual, items);
+
+        let evens = (&tree)
+            .into_iter()
+            .filter(|d| d.numerical_id % 2 == 0)
+            .count();
+        assert_eq!(evens, 25);
+        let mut ids = vec![];
+        for d in &tree {
+            ids.push(d.numerical_id);
+        }
+        assert_eq!(ids, (0..50).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn binary_search_tree_successor() {
+        let mut tree = DeviceRegistry::new_empty();
+        assert_eq!(tree.successor(1), None);
+
+        for id in [40, 20, 60, 10, 30, 50, 70] {
+            tree.add(new_device_with_id(id));
+        }
+        assert_eq!(tree.successor(0), Some(new_device_with_id(10)));
+        assert_eq!(tree.successor(10), Some(new_device_with_id(20)));
+        assert_eq!(tree.successor(30), Some(new_device_with_id(40)));
+        assert_eq!(tree.successor(35), Some(new_device_with_id(40)));
+        assert_eq!(tree.successor(40), Some(new_device_with_id(50)));
+        assert_eq!(tree.successor(69), Some(new_device_with_id(70)));
+        assert_eq!(tree.successor(70), None);
+        assert_eq!(tree.successor(100), None);
+
+        let mut ids = vec![];
+        let mut next = tree.min();
+        while let Some(dev) = next {
+            ids.push(dev.numerical_id);
+            next = tree.successor(dev.numerical_id);
+        }
+        assert_eq!(ids, vec![10, 20, 30, 40, 50, 60, 70]);
+    }
+
+    #[test]
+    fn binary_search_tree_count_leaves() {
+        let mut tree = DeviceRegistry::new_empty();
+        assert_eq!(tree.count_leaves(), 0);
+
+        tree.add(new_device_with_id(4));
+        assert_eq!(tree.count_leaves(), 1);
+
+        //        4
+        //      /   \
+        //     2     6
+        //    / \     \
+        //   1   3     8
+        //            /
+        //           7
+        for id in [2, 6, 1, 3, 8, 7] {
+            tree.add(new_device_with_id(id));
+        }
+        assert_eq!(tree.count_leaves(), 3);
+
+        // a chain has a single leaf
+        let mut chain = DeviceRegistry::new_empty();
+        for id in 0..10 {
+            chain.add(new_device_with_id(id));
+        }
+        assert_eq!(chain.count_leaves(), 1);
+    }
+
+    #[test]
+    fn binary_search_tree_contains() {
+        let mut tree = DeviceRegistry::new_empty();
+        assert!(!tree.contains(0));
+
+        let mut items: Vec<IoTDevice> = (0..30).map(|i| new_device_with_id(i * 2)).collect();
+        let mut rng = thread_rng();
+        rng.shuffle(&mut items);
+        for item in items.iter() {
+            tree.add(item.clone());
+        }
+
+        for item in items.iter() {
+            assert!(tree.contains(item.numerical_id));
+            assert!(!tree.contains(item.numerical_id + 1));
+        }
+        assert!(!tree.contains(100));
+    }
+
+    #[test]
+    fn binary_search_tree_from_sorted() {
+        let tree = DeviceRegistry::from_sorted(&[]);
+        assert_eq!(tree.length, 0);
+        assert!(tree.is_balanced());
+
+        for len in [1, 2, 7, 100, 1000] {
+            let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+            let tree = DeviceRegistry::from_sorted(&items);
+            assert_eq!(tree.length, len);
+            assert!(tree.is_balanced());
+            for item in items.iter() {
+                assert_eq!(tree.find(item.numerical_id), Some(item.clone()));
+            }
+        }
+
+        let mut chain = DeviceRegistry::new_empty();
+        for id in 0..3 {
+            chain.add(new_device_with_id(id));
+        }
+        assert!(!chain.is_balanced());
+        chain.rebalance();
+        assert!(chain.is_balanced());
+    }
+
+    #[test]
+    fn binary_search_tree_walk_reverse() {
+        let mut tree = DeviceRegistry::new_empty();
+        let mut items: Vec<IoTDevice> = (0..20).map(new_device_with_id).collect();
+
+        let mut rng = thread_rng();
+        rng.shuffle(&mut items);
+        for item in items.iter() {
+            tree.add(item.clone());
+        }
+
+        let v: RefCell<Vec<IoTDevice>> = RefCell::new(vec![]);
+        tree.walk_reverse(|n| v.borrow_mut().push(n.clone()));
+        // sort in descending order:
+        items.sort_by_key(|d| cmp::Reverse(d.numerical_id));
+        assert_eq!(v.into_inner(), items);
+
+        let empty = DeviceRegistry::new_empty();
+        empty.walk_reverse(|_| panic!("the tree is empty"));
+    }
+
+    #[test]
+    fn binary_search_tree_add_unique() {
+        let mut tree = DeviceRegistry::new_empty();
+        assert!(tree.add_unique(new_device_with_id_path(1, "first")));
+        assert!(!tree.add_unique(new_device_with_id_path(1, "second")));
+        assert_eq!(tree.length, 1);
+        assert_eq!(tree.find(1).map(|d| d.path), Some("first".to_string()));
+
+        for id in [5, 3, 8, 1, 3, 9, 5] {
+            tree.add_unique(new_device_with_id(id));
+        }
+        assert_eq!(tree.length, 5);
+        assert_eq!(
+            tree.iter().map(|d| d.numerical_id).collect::<Vec<u64>>(),
+            vec![1, 3, 5, 8, 9]
+        );
+    }
 }
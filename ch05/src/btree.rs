@@ -1,8 +1,13 @@
 use std::cmp;
 use std::collections::HashMap;
+use std::iter;
 use std::mem;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct IoTDevice {
     pub numerical_id: u64,
     pub path: String,
@@ -153,6 +158,83 @@ impl Node {
         result
     }
 
+    pub fn get_device_mut(&mut self, key: KeyType) -> Option<&mut IoTDevice> {
+        self.devices
+            .iter_mut()
+            .flatten()
+            .find(|device| device.numerical_id == key)
+    }
+
+    pub fn subtrees(&self) -> impl Iterator<Item = &Tree> {
+        iter::once(&self.left_child)
+            .chain(self.children.iter())
+            .flatten()
+    }
+
+    pub fn child_index(&self, key: KeyType) -> usize {
+        match self.find_closest_index(key) {
+            Direction::Left => 0,
+            Direction::Right(i) => i + 1,
+        }
+    }
+
+    // Child 0 is the left child, child i + 1 sits to the right of device i
+    pub fn child(&self, index: usize) -> Option<&Tree> {
+        if index == 0 {
+            self.left_child.as_ref()
+        } else {
+            self.children[index - 1].as_ref()
+        }
+    }
+
+    pub fn child_mut(&mut self, index: usize) -> &mut Option<Tree> {
+        if index == 0 {
+            &mut self.left_child
+        } else {
+            &mut self.children[index - 1]
+        }
+    }
+
+    // Moves the last device of the child left of `sep` up into the parent and
+    // the separator down into the child right of it
+    pub fn rotate_right(&mut self, sep: usize) {
+        let mut left = self.child_mut(sep).take().unwrap();
+        let mut right = self.child_mut(sep + 1).take().unwrap();
+
+        let separator = mem::replace(&mut self.devices[sep], left.devices.pop().unwrap());
+        right.devices.insert(0, separator);
+        right.children.insert(0, right.left_child.take());
+        right.left_child = left.children.pop().unwrap();
+
+        *self.child_mut(sep) = Some(left);
+        *self.child_mut(sep + 1) = Some(right);
+    }
+
+    pub fn rotate_left(&mut self, sep: usize) {
+        let mut left = self.child_mut(sep).take().unwrap();
+        let mut right = self.child_mut(sep + 1).take().unwrap();
+
+        let separator = mem::replace(&mut self.devices[sep], right.devices.remove(0));
+        left.devices.push(separator);
+        left.children.push(right.left_child.take());
+        right.left_child = right.children.remove(0);
+
+        *self.child_mut(sep) = Some(left);
+        *self.child_mut(sep + 1) = Some(right);
+    }
+
+    // Pulls the separator down and appends the child right of it to the one on the left
+    pub fn merge_children(&mut self, sep: usize) {
+        let separator = self.devices.remove(sep);
+        let right = *self.children.remove(sep).unwrap();
+        let left = self.child_mut(sep).as_mut().unwrap();
+
+        left.devices.push(separator);
+        left.children.push(right.left_child);
+        left.devices.extend(right.devices);
+        left.children.extend(right.children);
+    }
+
     pub fn get_child(&self, key: KeyType) -> Option<&Tree> {
         match self.find_closest_index(key) {
             Direction::Left => self.left_child.as_ref(),
@@ -176,6 +258,131 @@ impl DeviceDatabase {
         }
     }
 
+    // Builds the tree bottom-up from devices sorted by id, filling every node as
+    // much as the order allows.
+    pub fn from_sorted(order: usize, devices: Vec<IoTDevice>) -> DeviceDatabase {
+        let mut db = DeviceDatabase::new_empty(order);
+        db.length = devices.len() as u64;
+        if devices.is_empty() {
+            return db;
+        }
+
+        let (mut devices, mut nodes) = DeviceDatabase::build_level(order, devices, None);
+        while nodes.len() > 1 {
+            let level = DeviceDatabase::build_level(order, devices, Some(nodes));
+            devices = level.0;
+            nodes = level.1;
+        }
+        db.root = nodes.pop();
+        db
+    }
+
+    // Spreads `devices` evenly over as few nodes as possible, keeping one
+    // device between each pair of nodes aside as separator for the next level.
+    // `children` holds one more subtree than there are devices.
+    fn build_level(
+        order: usize,
+        devices: Vec<IoTDevice>,
+        children: Option<Vec<Tree>>,
+    ) -> (Vec<IoTDevice>, Vec<Tree>) {
+        let no_of_nodes = (devices.len() + order) / order;
+        let per_node = devices.len() - (no_of_nodes - 1);
+        let (base, extra) = (per_node / no_of_nodes, per_node % no_of_nodes);
+
+        let mut devices = devices.into_iter();
+        let mut children = children.map(|c| c.into_iter());
+        let mut separators = vec![];
+        let mut nodes = vec![];
+
+        for i in 0..no_of_nodes {
+            let mut node = match children {
+                Some(_) => Node::new_regular(),
+                None => Node::new_leaf(),
+            };
+            node.add_left_child(children.as_mut().and_then(|c| c.next()));
+            for _ in 0..base + if i < extra { 1 } else { 0 } {
+                node.devices.push(devices.next());
+                node.children.push(children.as_mut().and_then(|c| c.next()));
+            }
+            nodes.push(node);
+
+            if i + 1 < no_of_nodes {
+                separators.push(devices.next().unwrap());
+            }
+        }
+        (separators, nodes)
+    }
+
+    // Rebuilds the tree once with the new devices instead of adding one at a time
+    pub fn add_many(&mut self, devices: Vec<IoTDevice>) {
+        let mut all = self.to_vec();
+        all.extend(devices);
+        // a stable sort that finds the already sorted run and merges the rest into it
+        all.sort_by_key(|d| d.numerical_id);
+        *self = DeviceDatabase::from_sorted(self.order, all);
+    }
+
+    // Combines both trees into one of `self`'s order, keeping `self`'s device
+    // wherever both hold the same id. The devices stay ordered by id.
+    pub fn merge(&mut self, other: DeviceDatabase) {
+        let mut merged = Vec::with_capacity((self.length + other.length) as usize);
+        let mut mine = self.iter().peekable();
+        let mut theirs = other.iter().peekable();
+        loop {
+            let next = match (mine.peek(), theirs.peek()) {
+                (Some(a), Some(b)) => match a.numerical_id.cmp(&b.numerical_id) {
+                    cmp::Ordering::Less => mine.next(),
+                    cmp::Ordering::Greater => theirs.next(),
+                    cmp::Ordering::Equal => {
+                        theirs.next();
+                        mine.next()
+                    }
+                },
+                (Some(_), None) => mine.next(),
+                (None, Some(_)) => theirs.next(),
+                (None, None) => break,
+            };
+            merged.extend(next.cloned());
+        }
+        *self = DeviceDatabase::from_sorted(self.order, merged);
+    }
+
+    pub fn keep_smallest(&mut self, n: usize) {
+        let mut devices = self.to_vec();
+        devices.truncate(n);
+        *self = DeviceDatabase::from_sorted(self.order, devices);
+    }
+
+    // Rebuilds the tree from its sorted contents with every node as full as possible
+    pub fn rebalance(&mut self) {
+        let devices = self.to_vec();
+        *self = DeviceDatabase::from_sorted(self.order, devices);
+    }
+
+    // Share of device slots in use across all nodes, between 0 and 1
+    pub fn fill_factor(&self) -> f64 {
+        match self.node_count() {
+            0 => 0.0,
+            nodes => self.length as f64 / (nodes * (self.order - 1)) as f64,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.root.as_ref().map_or(0, |root| self.shape(root).0)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.root.as_ref().map_or(0, |root| self.shape(root).1)
+    }
+
+    // (levels, nodes) of the subtree
+    fn shape(&self, node: &Tree) -> (usize, usize) {
+        node.subtrees().fold((1, 1), |(height, count), child| {
+            let (h, c) = self.shape(child);
+            (cmp::max(height, h + 1), count + c)
+        })
+    }
+
     pub fn add(&mut self, device: IoTDevice) {
         let node = if self.root.is_some() {
             mem::replace(&mut self.root, None).unwrap()
@@ -232,6 +439,107 @@ impl DeviceDatabase {
         }
     }
 
+    // A deep copy of the whole tree, O(n) in time and memory
+    pub fn snapshot(&self) -> DeviceDatabase {
+        DeviceDatabase {
+            root: self.root.clone(),
+            order: self.order,
+            length: self.length,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: DeviceDatabase) {
+        *self = snapshot;
+    }
+
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.length = 0;
+    }
+
+    pub fn remove(&mut self, id: KeyType) -> Option<IoTDevice> {
+        let mut root = self.root.take()?;
+        let removed = self.remove_r(&mut root, id);
+        if removed.is_some() {
+            self.length -= 1;
+        }
+
+        // An empty root is replaced by its only child, which is None for a leaf
+        self.root = if root.devices.is_empty() {
+            root.left_child.take()
+        } else {
+            Some(root)
+        };
+        removed
+    }
+
+    fn remove_r(&self, node: &mut Tree, id: KeyType) -> Option<IoTDevice> {
+        let position = node
+            .devices
+            .iter()
+            .position(|d| matches!(d, Some(d) if d.numerical_id == id));
+
+        match (position, &node.node_type) {
+            (Some(i), NodeType::Leaf) => {
+                node.children.remove(i);
+                node.devices.remove(i)
+            }
+            (Some(i), NodeType::Regular) => {
+                // replace the device with its in-order predecessor, the largest
+                // device in the subtree to its left
+                let predecessor = self.remove_max(node.child_mut(i).as_mut().unwrap());
+                let dev = node.devices[i].replace(predecessor);
+                self.fix_underflow(node, i);
+                dev
+            }
+            (None, NodeType::Leaf) => None,
+            (None, NodeType::Regular) => {
+                let index = node.child_index(id);
+                let dev = self.remove_r(node.child_mut(index).as_mut().unwrap(), id);
+                if dev.is_some() {
+                    self.fix_underflow(node, index);
+                }
+                dev
+            }
+        }
+    }
+
+    fn remove_max(&self, node: &mut Tree) -> IoTDevice {
+        match node.node_type {
+            NodeType::Leaf => {
+                node.children.pop();
+                node.devices.pop().unwrap().unwrap()
+            }
+            NodeType::Regular => {
+                let last = node.devices.len();
+                let dev = self.remove_max(node.child_mut(last).as_mut().unwrap());
+                self.fix_underflow(node, last);
+                dev
+            }
+        }
+    }
+
+    // Refills the child at `index` if it dropped below the minimum number of
+    // devices, borrowing from a sibling that can spare one or merging otherwise
+    fn fix_underflow(&self, node: &mut Tree, index: usize) {
+        let min_devices = (self.order - 1) / 2;
+        let devices_at = |i: usize| node.child(i).unwrap().devices.len();
+
+        if devices_at(index) >= min_devices {
+            return;
+        }
+
+        if index > 0 && devices_at(index - 1) > min_devices {
+            node.rotate_right(index - 1);
+        } else if index < node.devices.len() && devices_at(index + 1) > min_devices {
+            node.rotate_left(index);
+        } else if index > 0 {
+            node.merge_children(index - 1);
+        } else {
+            node.merge_children(index);
+        }
+    }
+
     pub fn is_a_valid_btree(&self) -> bool {
         if let Some(tree) = self.root.as_ref() {
             let total = self.validate(tree, 0);
@@ -267,16 +575,95 @@ impl DeviceDatabase {
         }
     }
 
+    // Number of nodes on each level, starting with the root
+    pub fn level_stats(&self) -> Vec<usize> {
+        self.levels().into_iter().map(|(nodes, _)| nodes).collect()
+    }
+
+    // Average number of children of the nodes on each level, starting with the root
+    pub fn fanout_stats(&self) -> Vec<(usize, f64)> {
+        self.levels()
+            .into_iter()
+            .enumerate()
+            .map(|(level, (nodes, children))| (level, children as f64 / nodes as f64))
+            .collect()
+    }
+
+    // (nodes, children of those nodes) for each level
+    fn levels(&self) -> Vec<(usize, usize)> {
+        let mut levels = vec![];
+        if let Some(ref root) = self.root {
+            self.levels_r(root, 0, &mut levels);
+        }
+        levels
+    }
+
+    fn levels_r(&self, node: &Tree, level: usize, levels: &mut Vec<(usize, usize)>) {
+        if levels.len() <= level {
+            levels.push((0, 0));
+        }
+        levels[level].0 += 1;
+        for tree in node.subtrees() {
+            levels[level].1 += 1;
+            self.levels_r(tree, level + 1, levels);
+        }
+    }
+
+    pub fn max_depth_node(&self) -> Option<(usize, Vec<KeyType>)> {
+        self.root
+            .as_ref()
+            .map(|root| self.max_depth_node_r(root, 0))
+    }
+
+    fn max_depth_node_r(&self, node: &Tree, depth: usize) -> (usize, Vec<KeyType>) {
+        let keys = node
+            .devices
+            .iter()
+            .flatten()
+            .map(|d| d.numerical_id)
+            .collect();
+        let mut deepest = (depth, keys);
+        for tree in node.subtrees() {
+            let candidate = self.max_depth_node_r(tree, depth + 1);
+            if candidate.0 > deepest.0 {
+                deepest = candidate;
+            }
+        }
+        deepest
+    }
+
     pub fn find(&self, id: KeyType) -> Option<IoTDevice> {
         match self.root.as_ref() {
-            Some(tree) => self.find_r(tree, id),
+            Some(tree) => self.find_r(tree, id).cloned(),
             _ => None,
         }
     }
 
-    fn find_r(&self, node: &Tree, id: KeyType) -> Option<IoTDevice> {
+    // Replaces the device with the same id, leaving the tree untouched if there is none
+    pub fn update(&mut self, device: IoTDevice) -> Option<IoTDevice> {
+        let id = device.numerical_id;
+        let mut node = self.root.as_mut()?;
+        loop {
+            if let Some(dev) = node.get_device_mut(id) {
+                return Some(mem::replace(dev, device));
+            }
+            node = match node.find_closest_index(id) {
+                Direction::Left => node.left_child.as_mut()?,
+                Direction::Right(i) => node.children[i].as_mut()?,
+            };
+        }
+    }
+
+    pub fn contains(&self, id: KeyType) -> bool {
+        match self.root.as_ref() {
+            Some(tree) => self.find_r(tree, id).is_some(),
+            _ => false,
+        }
+    }
+
+    fn find_r<'a>(&self, node: &'a Tree, id: KeyType) -> Option<&'a IoTDevice> {
         match node.get_device(id) {
-            Some(device) => Some(device.clone()),
+            Some(device) => Some(device),
             None if node.node_type != NodeType::Leaf => {
                 if let Some(tree) = node.get_child(id) {
                     self.find_r(tree, id)
@@ -288,13 +675,155 @@ impl DeviceDatabase {
         }
     }
 
-    pub fn walk(&self, callback: impl Fn(&IoTDevice) -> ()) {
+    pub fn leaf_partitions(&self) -> Vec<Vec<IoTDevice>> {
+        let mut partitions = vec![];
+        if let Some(ref root) = self.root {
+            self.leaf_partitions_r(root, &mut partitions);
+        }
+        partitions
+    }
+
+    fn leaf_partitions_r(&self, node: &Tree, partitions: &mut Vec<Vec<IoTDevice>>) {
+        match node.node_type {
+            NodeType::Leaf => partitions.push(node.devices.iter().flatten().cloned().collect()),
+            NodeType::Regular => {
+                for child in node.subtrees() {
+                    self.leaf_partitions_r(child, partitions);
+                }
+            }
+        }
+    }
+
+    // `add` doesn't reject an id that is already stored; this lists every such
+    // id once, in ascending order
+    pub fn duplicate_ids(&self) -> Vec<KeyType> {
+        let mut duplicates = vec![];
+        let mut previous = None;
+        for d in self.iter() {
+            if previous == Some(d.numerical_id) && duplicates.last() != previous.as_ref() {
+                duplicates.push(d.numerical_id);
+            }
+            previous = Some(d.numerical_id);
+        }
+        duplicates
+    }
+
+    // A full scan, the tree is only indexed by id
+    pub fn count_by_path_prefix(&self, prefix: &str) -> usize {
+        self.iter().filter(|d| d.path.starts_with(prefix)).count()
+    }
+
+    pub fn min(&self) -> Option<IoTDevice> {
+        let mut node = self.root.as_ref()?;
+        while let Some(ref left) = node.left_child {
+            node = left;
+        }
+        node.devices.first().cloned().flatten()
+    }
+
+    pub fn max(&self) -> Option<IoTDevice> {
+        let mut node = self.root.as_ref()?;
+        while let Some(Some(ref right)) = node.children.last() {
+            node = right;
+        }
+        node.devices.last().cloned().flatten()
+    }
+
+    pub fn range(&self, low: KeyType, high: KeyType) -> Vec<IoTDevice> {
+        let mut devices = vec![];
+        if let Some(ref root) = self.root {
+            if low <= high {
+                self.range_r(root, low, high, &mut devices);
+            }
+        }
+        devices
+    }
+
+    fn range_r(&self, node: &Tree, low: KeyType, high: KeyType, devices: &mut Vec<IoTDevice>) {
+        // only the children between the last device below `low` and the last
+        // device up to `high` can hold devices in range
+        let first = if low > 0 {
+            node.child_index(low - 1)
+        } else {
+            0
+        };
+        let last = node.child_index(high);
+
+        for i in first..=last {
+            if let Some(child) = node.child(i) {
+                self.range_r(child, low, high, devices);
+            }
+            if i < last {
+                if let Some(ref dev) = node.devices[i] {
+                    devices.push(dev.clone());
+                }
+            }
+        }
+    }
+
+    pub fn walk(&self, mut callback: impl Fn(&IoTDevice) -> ()) {
+        if let Some(ref root) = self.root {
+            self.walk_in_order(root, &mut callback);
+        }
+    }
+
+    // A chunk size of 0 has nothing to hand out, so the callback never runs
+    pub fn walk_chunked(&self, chunk_size: usize, mut callback: impl FnMut(&[IoTDevice])) {
+        if chunk_size == 0 {
+            return;
+        }
+        let mut chunk = Vec::with_capacity(chunk_size);
         if let Some(ref root) = self.root {
-            self.walk_in_order(root, &callback);
+            self.walk_in_order(root, &mut |dev: &IoTDevice| {
+                chunk.push(dev.clone());
+                if chunk.len() == chunk_size {
+                    callback(&chunk);
+                    chunk.clear();
+                }
+            });
+        }
+        if !chunk.is_empty() {
+            callback(&chunk);
         }
     }
 
-    fn walk_in_order(&self, node: &Tree, callback: &impl Fn(&IoTDevice) -> ()) {
+    pub fn to_vec(&self) -> Vec<IoTDevice> {
+        self.iter().cloned().collect()
+    }
+
+    // Only the devices are written out, the node layout is rebuilt on load
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        let devices: Vec<&IoTDevice> = self.iter().collect();
+        serde_json::to_string(&devices).unwrap()
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(order: usize, json: &str) -> Result<DeviceDatabase, serde_json::Error> {
+        let devices: Vec<IoTDevice> = serde_json::from_str(json)?;
+        let mut db = DeviceDatabase::new_empty(order);
+        db.add_many(devices);
+        Ok(db)
+    }
+
+    pub fn iter(&self) -> BTreeIter<'_> {
+        BTreeIter::new(self.root.as_ref())
+    }
+
+    // Index of the device in ascending id order; the nodes don't track
+    // subtree sizes, so this walks everything before it
+    pub fn position_of(&self, id: KeyType) -> Option<usize> {
+        self.iter()
+            .take_while(|d| d.numerical_id <= id)
+            .position(|d| d.numerical_id == id)
+    }
+
+    // Starts at the first device with an id of at least `start`
+    pub fn iter_from(&self, start: KeyType) -> BTreeIter<'_> {
+        BTreeIter::seek(self.root.as_ref(), start)
+    }
+
+    fn walk_in_order(&self, node: &Tree, callback: &mut impl FnMut(&IoTDevice)) {
         if let Some(ref left) = node.left_child {
             self.walk_in_order(left, callback);
         }
@@ -309,6 +838,111 @@ impl DeviceDatabase {
             }
         }
     }
+
+    pub fn walk_desc(&self, callback: impl Fn(&IoTDevice)) {
+        if let Some(ref root) = self.root {
+            self.walk_in_reverse_order(root, &callback);
+        }
+    }
+
+    fn walk_in_reverse_order(&self, node: &Tree, callback: &impl Fn(&IoTDevice)) {
+        for i in (0..node.devices.len()).rev() {
+            if let Some(ref c) = node.children[i] {
+                self.walk_in_reverse_order(c, callback);
+            }
+
+            if let Some(ref k) = node.devices[i] {
+                callback(k);
+            }
+        }
+
+        if let Some(ref left) = node.left_child {
+            self.walk_in_reverse_order(left, callback);
+        }
+    }
+}
+
+impl Default for DeviceDatabase {
+    fn default() -> DeviceDatabase {
+        DeviceDatabase::new_empty(3)
+    }
+}
+
+// Two databases are equal if they hold the same devices, whatever the order
+// or shape of the trees
+impl PartialEq for DeviceDatabase {
+    fn eq(&self, other: &DeviceDatabase) -> bool {
+        self.length == other.length && self.iter().eq(other.iter())
+    }
+}
+
+impl<'a> IntoIterator for &'a DeviceDatabase {
+    type Item = &'a IoTDevice;
+    type IntoIter = BTreeIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct BTreeIter<'a> {
+    // nodes on the path to the current device, each with the index of the
+    // next device to yield from it
+    stack: Vec<(&'a Node, usize)>,
+}
+
+impl<'a> BTreeIter<'a> {
+    fn new(root: Option<&'a Tree>) -> BTreeIter<'a> {
+        let mut iter = BTreeIter { stack: vec![] };
+        if let Some(root) = root {
+            iter.descend(root);
+        }
+        iter
+    }
+
+    fn seek(root: Option<&'a Tree>, start: KeyType) -> BTreeIter<'a> {
+        let mut iter = BTreeIter { stack: vec![] };
+        let mut node = root;
+        while let Some(n) = node {
+            // skip the devices below `start` and continue left of the next one
+            let i = if start > 0 {
+                n.child_index(start - 1)
+            } else {
+                0
+            };
+            iter.stack.push((n, i));
+            node = n.child(i);
+        }
+        iter
+    }
+
+    fn descend(&mut self, node: &'a Tree) {
+        let mut node = node;
+        self.stack.push((node, 0));
+        while let Some(ref left) = node.left_child {
+            node = left;
+            self.stack.push((node, 0));
+        }
+    }
+}
+
+impl<'a> Iterator for BTreeIter<'a> {
+    type Item = &'a IoTDevice;
+
+    fn next(&mut self) -> Option<&'a IoTDevice> {
+        while let Some((node, i)) = self.stack.pop() {
+            if i < node.devices.len() {
+                self.stack.push((node, i + 1));
+                if let Some(ref child) = node.children[i] {
+                    self.descend(child);
+                }
+                if let Some(ref dev) = node.devices[i] {
+                    return Some(dev);
+                }
+            }
+        }
+        None
+    }
 }
 
 #[cfg(test)]
@@ -317,6 +951,7 @@ mod tests {
     use rand::thread_rng;
     use rand::Rng;
     use std::cell::RefCell;
+    use std::time::Instant;
 
     fn new_device_with_id(id: u64) -> IoTDevice {
         new_device_with_id_path(id, "")
@@ -388,4 +1023,725 @@ mod tests {
         assert_eq!(tree.find(6), Some(new_device_with_id(6)));
         assert_eq!(tree.find(7), Some(new_device_with_id(7)));
     }
+
+    fn leaf_depths(node: &Tree, depth: usize, depths: &mut Vec<usize>) {
+        if node.node_type == NodeType::Leaf {
+            depths.push(depth);
+        }
+        for tree in node.subtrees() {
+            leaf_depths(tree, depth + 1, depths);
+        }
+    }
+
+    #[test]
+    fn btree_max_depth_node() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert_eq!(tree.max_depth_node(), None);
+
+        tree.add(new_device_with_id(1));
+        assert_eq!(tree.max_depth_node(), Some((0, vec![1])));
+
+        let mut items: Vec<IoTDevice> = (2..100).map(new_device_with_id).collect();
+        let mut rng = thread_rng();
+        rng.shuffle(&mut items);
+        for item in items {
+            tree.add(item);
+        }
+        assert!(tree.is_a_valid_btree());
+
+        let root = tree.root.as_ref().unwrap();
+        let mut depths = vec![];
+        leaf_depths(root, 0, &mut depths);
+        assert!(depths.iter().all(|d| *d == depths[0]));
+
+        let (depth, keys) = tree.max_depth_node().unwrap();
+        assert_eq!(depth, tree.height() - 1);
+        assert!(!keys.is_empty());
+        assert!(keys.iter().all(|k| tree.find(*k).is_some()));
+    }
+
+    #[test]
+    fn btree_snapshot_restore() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        for id in 0..10 {
+            tree.add(new_device_with_id(id));
+        }
+
+        let snapshot = tree.snapshot();
+        for id in 10..20 {
+            tree.add(new_device_with_id(id));
+        }
+        assert_eq!(tree.length, 20);
+        assert_eq!(tree.find(15), Some(new_device_with_id(15)));
+        assert_eq!(snapshot.length, 10);
+        assert_eq!(snapshot.find(15), None);
+
+        tree.restore(snapshot);
+        assert!(tree.is_a_valid_btree());
+        assert_eq!(tree.length, 10);
+        let v: RefCell<Vec<IoTDevice>> = RefCell::new(vec![]);
+        tree.walk(|n| v.borrow_mut().push(n.clone()));
+        assert_eq!(
+            v.into_inner(),
+            (0..10).map(new_device_with_id).collect::<Vec<IoTDevice>>()
+        );
+    }
+
+    #[test]
+    fn btree_walk_chunked() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        let mut items: Vec<IoTDevice> = (0..23).map(new_device_with_id).collect();
+
+        let mut rng = thread_rng();
+        rng.shuffle(&mut items);
+        for item in items {
+            tree.add(item);
+        }
+        assert_eq!(tree.to_vec().len(), 23);
+
+        let mut chunks: Vec<Vec<IoTDevice>> = vec![];
+        tree.walk_chunked(5, |chunk| chunks.push(chunk.to_vec()));
+        assert_eq!(chunks.len(), 5);
+        assert!(chunks[..4].iter().all(|c| c.len() == 5));
+        assert_eq!(chunks[4].len(), 3);
+        assert_eq!(chunks.concat(), tree.to_vec());
+
+        let mut chunks: Vec<Vec<IoTDevice>> = vec![];
+        tree.walk_chunked(100, |chunk| chunks.push(chunk.to_vec()));
+        assert_eq!(chunks, vec![tree.to_vec()]);
+
+        let mut calls = 0;
+        DeviceDatabase::new_empty(3).walk_chunked(5, |_| calls += 1);
+        assert_eq!(calls, 0);
+        tree.walk_chunked(0, |_| calls += 1);
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn btree_remove() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert_eq!(tree.remove(1), None);
+
+        tree.add(new_device_with_id(1));
+        tree.add(new_device_with_id(2));
+        tree.add(new_device_with_id(3));
+        assert_eq!(tree.max_depth_node().unwrap().0, 1);
+
+        // removing the only device of the root merges both leaves into the new root
+        assert_eq!(tree.remove(2), Some(new_device_with_id(2)));
+        assert!(tree.is_a_valid_btree());
+        assert_eq!(tree.max_depth_node(), Some((0, vec![1, 3])));
+        assert_eq!(tree.length, 2);
+
+        assert_eq!(tree.remove(2), None);
+        assert_eq!(tree.length, 2);
+
+        assert_eq!(tree.remove(1), Some(new_device_with_id(1)));
+        assert_eq!(tree.remove(3), Some(new_device_with_id(3)));
+        assert_eq!(tree.length, 0);
+        assert_eq!(tree.find(3), None);
+        assert!(!tree.is_a_valid_btree());
+
+        tree.add(new_device_with_id(4));
+        assert!(tree.is_a_valid_btree());
+        assert_eq!(tree.find(4), Some(new_device_with_id(4)));
+    }
+
+    #[test]
+    fn btree_remove_random() {
+        let len = 200;
+        let mut rng = thread_rng();
+
+        for order in 3..8 {
+            let mut tree = DeviceDatabase::new_empty(order);
+            let mut items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+            rng.shuffle(&mut items);
+            for item in items.iter() {
+                tree.add(item.clone());
+            }
+
+            rng.shuffle(&mut items);
+            for (i, item) in items.iter().enumerate() {
+                assert_eq!(tree.remove(item.numerical_id), Some(item.clone()));
+                assert_eq!(tree.remove(item.numerical_id), None);
+                assert_eq!(tree.length, len - i as u64 - 1);
+                assert_eq!(tree.find(item.numerical_id), None);
+                if tree.length > 0 {
+                    assert!(tree.is_a_valid_btree());
+                }
+            }
+
+            for item in items.iter().skip(1) {
+                tree.add(item.clone());
+            }
+            assert_eq!(tree.length, len - 1);
+            assert!(tree.is_a_valid_btree());
+            assert_eq!(tree.to_vec().len(), len as usize - 1);
+        }
+    }
+
+    #[test]
+    fn btree_from_sorted() {
+        for order in 3..8 {
+            for len in 0..60 {
+                let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+                let tree = DeviceDatabase::from_sorted(order, items.clone());
+
+                assert_eq!(tree.length, len);
+                assert_eq!(tree.to_vec(), items);
+                if len > 0 {
+                    assert!(tree.is_a_valid_btree());
+                }
+                for id in 0..len {
+                    assert_eq!(tree.find(id), Some(new_device_with_id(id)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn btree_rebalance() {
+        let mut tree = DeviceDatabase::new_empty(5);
+        let mut rng = thread_rng();
+        let mut items: Vec<IoTDevice> = (0..500).map(new_device_with_id).collect();
+        rng.shuffle(&mut items);
+        for item in items.iter() {
+            tree.add(item.clone());
+        }
+
+        let before = tree.fill_factor();
+        let contents = tree.to_vec();
+        tree.rebalance();
+
+        assert!(tree.fill_factor() > before);
+        assert!(tree.is_a_valid_btree());
+        assert_eq!(tree.length, 500);
+        assert_eq!(tree.to_vec(), contents);
+
+        let mut empty = DeviceDatabase::new_empty(5);
+        empty.rebalance();
+        assert_eq!(empty.fill_factor(), 0.0);
+        assert_eq!(empty.length, 0);
+    }
+
+    #[test]
+    fn btree_range() {
+        let mut tree = DeviceDatabase::new_empty(4);
+        assert_eq!(tree.range(0, 100), vec![]);
+
+        let mut rng = thread_rng();
+        let mut items: Vec<IoTDevice> = (0..50).map(|i| new_device_with_id(i * 2)).collect();
+        rng.shuffle(&mut items);
+        for item in items.iter() {
+            tree.add(item.clone());
+        }
+
+        let ids = |low: u64, high: u64| -> Vec<u64> {
+            tree.range(low, high)
+                .iter()
+                .map(|d| d.numerical_id)
+                .collect()
+        };
+        assert_eq!(ids(10, 20), vec![10, 12, 14, 16, 18, 20]);
+        assert_eq!(ids(11, 19), vec![12, 14, 16, 18]);
+        assert_eq!(ids(0, 4), vec![0, 2, 4]);
+        assert_eq!(ids(95, 1000), vec![96, 98]);
+        assert!(ids(13, 13).is_empty());
+        assert_eq!(ids(14, 14), vec![14]);
+        assert!(ids(30, 20).is_empty());
+        assert!(ids(200, 300).is_empty());
+        assert_eq!(tree.range(0, u64::MAX), tree.to_vec());
+    }
+
+    #[test]
+    fn btree_min_max() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert_eq!(tree.min(), None);
+        assert_eq!(tree.max(), None);
+
+        tree.add(new_device_with_id(7));
+        assert_eq!(tree.min(), Some(new_device_with_id(7)));
+        assert_eq!(tree.max(), Some(new_device_with_id(7)));
+
+        let mut rng = thread_rng();
+        let mut items: Vec<IoTDevice> = (10..110).map(new_device_with_id).collect();
+        rng.shuffle(&mut items);
+        for item in items {
+            tree.add(item);
+        }
+        assert_eq!(tree.min(), Some(new_device_with_id(7)));
+        assert_eq!(tree.max(), Some(new_device_with_id(109)));
+    }
+
+    #[test]
+    fn btree_iter() {
+        let mut tree = DeviceDatabase::new_empty(4);
+        assert_eq!(tree.iter().next(), None);
+
+        let mut rng = thread_rng();
+        let mut items: Vec<IoTDevice> = (0..100).map(new_device_with_id).collect();
+        rng.shuffle(&mut items);
+        for item in items.iter() {
+            tree.add(item.clone());
+        }
+
+        let mut ids = vec![];
+        for dev in &tree {
+            ids.push(dev.numerical_id);
+        }
+        assert_eq!(ids, (0..100).collect::<Vec<u64>>());
+
+        let mut iter = tree.iter();
+        let first: Vec<u64> = iter.by_ref().take(10).map(|d| d.numerical_id).collect();
+        assert_eq!(first, (0..10).collect::<Vec<u64>>());
+        assert_eq!(iter.next(), Some(&new_device_with_id(10)));
+
+        let even = tree.iter().filter(|d| d.numerical_id % 2 == 0).count();
+        assert_eq!(even, 50);
+    }
+
+    #[test]
+    fn btree_height_node_count() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert_eq!(tree.height(), 0);
+        assert_eq!(tree.node_count(), 0);
+
+        tree.add(new_device_with_id(0));
+        assert_eq!(tree.height(), 1);
+        assert_eq!(tree.node_count(), 1);
+
+        tree.add(new_device_with_id(1));
+        tree.add(new_device_with_id(2));
+        assert_eq!(tree.height(), 2);
+        assert_eq!(tree.node_count(), 3);
+
+        let len = 1000;
+        for id in 3..len {
+            tree.add(new_device_with_id(id));
+        }
+        assert!(tree.is_a_valid_btree());
+        // every inner node has at least two children
+        let bound = (len as f64).log2().ceil() as usize + 1;
+        assert!(tree.height() > 1);
+        assert!(tree.height() <= bound);
+        assert!(tree.node_count() <= len as usize);
+    }
+
+    #[test]
+    fn btree_leaf_partitions() {
+        let mut tree = DeviceDatabase::new_empty(4);
+        assert!(tree.leaf_partitions().is_empty());
+
+        tree.add(new_device_with_id(100));
+        tree.add(new_device_with_id(101));
+        assert_eq!(tree.leaf_partitions(), vec![tree.to_vec()]);
+
+        let mut rng = thread_rng();
+        let mut items: Vec<IoTDevice> = (0..100).map(new_device_with_id).collect();
+        rng.shuffle(&mut items);
+        for item in items {
+            tree.add(item);
+        }
+
+        let partitions = tree.leaf_partitions();
+        let mut depths = vec![];
+        leaf_depths(tree.root.as_ref().unwrap(), 0, &mut depths);
+        assert_eq!(partitions.len(), depths.len());
+        assert!(partitions.iter().all(|p| !p.is_empty()));
+
+        // the inner nodes hold the devices between two leaves
+        let flat: Vec<IoTDevice> = partitions.concat();
+        let leaf_devices: Vec<IoTDevice> = tree
+            .to_vec()
+            .into_iter()
+            .filter(|d| flat.contains(d))
+            .collect();
+        assert_eq!(flat, leaf_devices);
+    }
+
+    #[test]
+    fn btree_contains() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert!(!tree.contains(0));
+
+        for id in 0..50 {
+            tree.add(new_device_with_id(id * 3));
+        }
+        for id in 0..50 {
+            assert!(tree.contains(id * 3));
+            assert!(!tree.contains(id * 3 + 1));
+            assert!(!tree.contains(id * 3 + 2));
+        }
+        assert!(!tree.contains(1000));
+    }
+
+    #[test]
+    fn btree_update() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert_eq!(tree.update(new_device_with_id(1)), None);
+        assert_eq!(tree.length, 0);
+
+        for id in 0..20 {
+            tree.add(new_device_with_id(id));
+        }
+
+        for id in 0..20 {
+            let moved = IoTDevice::new(id, format!("New address {}", id), "factory2");
+            assert_eq!(tree.update(moved.clone()), Some(new_device_with_id(id)));
+            assert_eq!(tree.find(id), Some(moved));
+            assert_eq!(tree.find(id).unwrap().path, "factory2");
+        }
+        assert_eq!(tree.length, 20);
+        assert!(tree.is_a_valid_btree());
+
+        assert_eq!(tree.update(new_device_with_id(100)), None);
+        assert_eq!(tree.find(100), None);
+        assert_eq!(tree.length, 20);
+    }
+
+    #[test]
+    fn btree_iter_from() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert_eq!(tree.iter_from(0).next(), None);
+
+        let mut rng = thread_rng();
+        let mut items: Vec<IoTDevice> = (0..60).map(|i| new_device_with_id(i * 2)).collect();
+        rng.shuffle(&mut items);
+        for item in items {
+            tree.add(item);
+        }
+
+        for start in 0..125 {
+            let ids: Vec<u64> = tree.iter_from(start).map(|d| d.numerical_id).collect();
+            let expected: Vec<u64> = (0..60).map(|i| i * 2).filter(|i| *i >= start).collect();
+            assert_eq!(ids, expected);
+        }
+    }
+
+    #[test]
+    fn btree_add_many() {
+        let mut tree = DeviceDatabase::new_empty(5);
+        tree.add_many(vec![]);
+        assert_eq!(tree.length, 0);
+
+        tree.add(new_device_with_id(1));
+        tree.add(new_device_with_id(100));
+        tree.add_many((2..100).rev().map(new_device_with_id).collect());
+        assert!(tree.is_a_valid_btree());
+        assert_eq!(tree.length, 100);
+        assert_eq!(
+            tree.to_vec(),
+            (1..101).map(new_device_with_id).collect::<Vec<IoTDevice>>()
+        );
+    }
+
+    #[test]
+    fn btree_add_many_matches_add() {
+        let len = 50_000;
+        let mut rng = thread_rng();
+        let mut items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+        rng.shuffle(&mut items);
+
+        let mut one_by_one = DeviceDatabase::new_empty(5);
+        for item in items.iter() {
+            one_by_one.add(item.clone());
+        }
+        let mut bulk = DeviceDatabase::new_empty(5);
+        bulk.add_many(items);
+
+        assert!(bulk.is_a_valid_btree());
+        assert_eq!(bulk.length, len);
+        assert_eq!(bulk.to_vec(), one_by_one.to_vec());
+    }
+
+    // Timing depends on the machine and whatever else is running, so this
+    // only runs when asked for: cargo test -- --ignored
+    #[test]
+    #[ignore]
+    fn btree_add_many_is_faster() {
+        let len = 50_000;
+        let mut rng = thread_rng();
+        let mut items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+        rng.shuffle(&mut items);
+
+        let start = Instant::now();
+        let mut one_by_one = DeviceDatabase::new_empty(5);
+        for item in items.iter() {
+            one_by_one.add(item.clone());
+        }
+        let one_by_one_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut bulk = DeviceDatabase::new_empty(5);
+        bulk.add_many(items);
+        let bulk_time = start.elapsed();
+
+        assert!(bulk_time < one_by_one_time);
+    }
+
+    #[test]
+    fn btree_eq() {
+        let items: Vec<IoTDevice> = (0..100).map(new_device_with_id).collect();
+
+        let bulk = DeviceDatabase::from_sorted(3, items.clone());
+        let mut one_by_one = DeviceDatabase::new_empty(3);
+        for item in items.iter() {
+            one_by_one.add(item.clone());
+        }
+        assert!(bulk == one_by_one);
+        assert!(bulk == DeviceDatabase::from_sorted(7, items.clone()));
+
+        one_by_one.update(IoTDevice::new(50, "elsewhere", ""));
+        assert!(bulk != one_by_one);
+
+        one_by_one.remove(50);
+        assert!(bulk != one_by_one);
+        assert!(DeviceDatabase::new_empty(3) == DeviceDatabase::new_empty(5));
+    }
+
+    #[test]
+    fn btree_walk_desc() {
+        let len = 50;
+
+        let mut tree = DeviceDatabase::new_empty(4);
+        let mut items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut rng = thread_rng();
+        rng.shuffle(&mut items);
+
+        for item in items.iter() {
+            tree.add(item.clone());
+        }
+        let v: RefCell<Vec<IoTDevice>> = RefCell::new(vec![]);
+        tree.walk_desc(|n| v.borrow_mut().push(n.clone()));
+        items.sort_by_key(|d| cmp::Reverse(d.numerical_id));
+        assert_eq!(v.into_inner(), items);
+    }
+
+    #[test]
+    fn btree_count_by_path_prefix() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert_eq!(tree.count_by_path_prefix(""), 0);
+
+        for id in 0..30 {
+            tree.add(new_device_with_id_path(
+                id,
+                format!("factory{}/machineA/{}", id % 3, id),
+            ));
+        }
+        assert_eq!(tree.count_by_path_prefix(""), 30);
+        assert_eq!(tree.count_by_path_prefix("factory"), 30);
+        assert_eq!(tree.count_by_path_prefix("factory1"), 10);
+        assert_eq!(tree.count_by_path_prefix("factory1/machineA/1"), 5);
+        assert_eq!(tree.count_by_path_prefix("factory3"), 0);
+        assert_eq!(tree.count_by_path_prefix("machineA"), 0);
+    }
+
+    #[test]
+    fn btree_merge() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        tree.merge(DeviceDatabase::new_empty(5));
+        assert_eq!(tree.length, 0);
+
+        let mut rng = thread_rng();
+        let mut ids: Vec<u64> = (0..100).collect();
+        rng.shuffle(&mut ids);
+        for &id in ids.iter().filter(|&&id| id < 60) {
+            tree.add(new_device_with_id_path(id, "mine"));
+        }
+        let mut other = DeviceDatabase::new_empty(7);
+        for &id in ids.iter().filter(|&&id| id >= 40) {
+            other.add(new_device_with_id_path(id, "theirs"));
+        }
+
+        tree.merge(other);
+        assert!(tree.is_a_valid_btree());
+        assert_eq!(tree.length, 100);
+        assert_eq!(
+            tree.iter().map(|d| d.numerical_id).collect::<Vec<u64>>(),
+            (0..100).collect::<Vec<u64>>()
+        );
+        for d in tree.iter() {
+            let expected = if d.numerical_id < 60 {
+                "mine"
+            } else {
+                "theirs"
+            };
+            assert_eq!(d.path, expected);
+        }
+
+        let mut empty = DeviceDatabase::new_empty(4);
+        empty.merge(tree.snapshot());
+        assert!(empty.is_a_valid_btree());
+        assert!(empty == tree);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn btree_json_round_trip() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert_eq!(tree.to_json(), "[]");
+
+        let mut rng = thread_rng();
+        let mut items: Vec<IoTDevice> = (0..100)
+            .map(|id| new_device_with_id_path(id, format!("factory{}/machineA/{}", id % 4, id)))
+            .collect();
+        rng.shuffle(&mut items);
+        for item in items.iter() {
+            tree.add(item.clone());
+        }
+
+        let json = tree.to_json();
+        let loaded = DeviceDatabase::from_json(5, &json).unwrap();
+        assert!(loaded.is_a_valid_btree());
+        assert_eq!(loaded.length, tree.length);
+        assert!(loaded == tree);
+        for id in 0..110 {
+            let (before, after) = (tree.find(id), loaded.find(id));
+            assert_eq!(before, after);
+            assert_eq!(before.map(|d| d.path), after.map(|d| d.path));
+        }
+
+        assert!(DeviceDatabase::from_json(5, "{").is_err());
+    }
+
+    #[test]
+    fn btree_level_stats() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert!(tree.level_stats().is_empty());
+
+        tree.add(new_device_with_id(0));
+        assert_eq!(tree.level_stats(), vec![1]);
+
+        let tree = DeviceDatabase::from_sorted(3, (0..8).map(new_device_with_id).collect());
+        assert_eq!(tree.level_stats(), vec![1, 3]);
+
+        let tree = DeviceDatabase::from_sorted(3, (0..26).map(new_device_with_id).collect());
+        assert_eq!(tree.level_stats(), vec![1, 3, 9]);
+
+        let mut tree = DeviceDatabase::new_empty(5);
+        let mut rng = thread_rng();
+        for _ in 0..500 {
+            tree.add(new_device_with_id(rng.gen_range(0, 1000)));
+        }
+        let mut depths = vec![];
+        leaf_depths(tree.root.as_ref().unwrap(), 0, &mut depths);
+        let stats = tree.level_stats();
+        assert_eq!(stats.len(), tree.height());
+        assert_eq!(stats.iter().sum::<usize>(), tree.node_count());
+        assert_eq!(stats[0], 1);
+        assert_eq!(*stats.last().unwrap(), depths.len());
+    }
+
+    #[test]
+    fn btree_clear() {
+        let tree = DeviceDatabase::default();
+        assert_eq!(tree.order, 3);
+        assert_eq!(tree.length, 0);
+
+        let mut tree = DeviceDatabase::new_empty(5);
+        for id in 0..50 {
+            tree.add(new_device_with_id(id));
+        }
+        tree.clear();
+        assert_eq!(tree.length, 0);
+        assert_eq!(tree.order, 5);
+        assert!(!tree.is_a_valid_btree());
+        assert_eq!(tree.find(10), None);
+        assert_eq!(tree.iter().count(), 0);
+
+        for id in (0..20).rev() {
+            tree.add(new_device_with_id(id));
+        }
+        assert!(tree.is_a_valid_btree());
+        assert_eq!(tree.length, 20);
+        assert_eq!(tree.find(10), Some(new_device_with_id(10)));
+        assert_eq!(tree.find(30), None);
+    }
+
+    #[test]
+    fn btree_position_of() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert_eq!(tree.position_of(1), None);
+
+        let mut rng = thread_rng();
+        let mut ids: Vec<u64> = (0..200).map(|i| i * 3).collect();
+        rng.shuffle(&mut ids);
+        for id in ids {
+            tree.add(new_device_with_id(id));
+        }
+
+        let all = tree.to_vec();
+        for (i, d) in all.iter().enumerate() {
+            assert_eq!(tree.position_of(d.numerical_id), Some(i));
+        }
+        assert_eq!(tree.position_of(1), None);
+        assert_eq!(tree.position_of(598), None);
+        assert_eq!(tree.position_of(1000), None);
+    }
+
+    #[test]
+    fn btree_duplicate_ids() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        assert!(tree.duplicate_ids().is_empty());
+
+        for id in 0..10 {
+            tree.add(new_device_with_id(id));
+        }
+        assert!(tree.duplicate_ids().is_empty());
+
+        tree.add(new_device_with_id(3));
+        assert_eq!(tree.duplicate_ids(), vec![3]);
+
+        tree.add(new_device_with_id(3));
+        tree.add(new_device_with_id(9));
+        tree.add(new_device_with_id(0));
+        assert_eq!(tree.duplicate_ids(), vec![0, 3, 9]);
+    }
+
+    #[test]
+    fn btree_keep_smallest() {
+        let mut tree = DeviceDatabase::new_empty(3);
+        let mut items: Vec<IoTDevice> = (0..20).map(new_device_with_id).collect();
+        let mut rng = thread_rng();
+        rng.shuffle(&mut items);
+        for item in items {
+            tree.add(item);
+        }
+
+        tree.keep_smallest(50);
+        assert_eq!(tree.length, 20);
+
+        tree.keep_smallest(5);
+        assert!(tree.is_a_valid_btree());
+        assert_eq!(tree.length, 5);
+        assert_eq!(
+            tree.to_vec(),
+            (0..5).map(new_device_with_id).collect::<Vec<IoTDevice>>()
+        );
+        assert_eq!(tree.find(5), None);
+
+        tree.keep_smallest(0);
+        assert_eq!(tree.length, 0);
+        assert_eq!(tree.find(0), None);
+    }
+
+    #[test]
+    fn btree_fanout_stats() {
+        let tree = DeviceDatabase::new_empty(5);
+        assert!(tree.fanout_stats().is_empty());
+
+        let tree = DeviceDatabase::from_sorted(5, (0..1000).map(new_device_with_id).collect());
+        let stats = tree.fanout_stats();
+        assert_eq!(stats.len(), tree.height());
+        assert_eq!(stats.len(), tree.level_stats().len());
+        for (i, (level, fanout)) in stats.iter().enumerate() {
+            assert_eq!(*level, i);
+            if i + 1 < stats.len() && i > 0 {
+                assert!(*fanout >= 4.0 && *fanout <= 5.0);
+            }
+        }
+        assert!(stats[0].1 >= 2.0);
+        assert_eq!(stats.last().unwrap().1, 0.0);
+    }
 }
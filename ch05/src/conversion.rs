@@ -0,0 +1,64 @@
+use std::cell::RefCell;
+
+use crate::btree;
+use crate::btree::DeviceDatabase;
+use crate::trie::BestDeviceRegistry;
+
+// Indexes the devices of a trie by numerical_id, building the B-Tree in one go
+pub fn trie_to_btree(trie: &BestDeviceRegistry, order: usize) -> DeviceDatabase {
+    let devices: RefCell<Vec<btree::IoTDevice>> = RefCell::new(vec![]);
+    trie.walk(|d| {
+        devices.borrow_mut().push(btree::IoTDevice::new(
+            d.numerical_id,
+            d.address.clone(),
+            d.path.clone(),
+        ))
+    });
+
+    let mut db = DeviceDatabase::new_empty(order);
+    db.add_many(devices.into_inner());
+    db
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trie;
+    use rand::thread_rng;
+    use rand::Rng;
+
+    fn new_device_with_id_path(id: u64, path: impl Into<String>) -> trie::IoTDevice {
+        trie::IoTDevice::new(id, format!("My address is {}", id), path)
+    }
+
+    #[test]
+    fn conversion_trie_to_btree() {
+        let db = trie_to_btree(&BestDeviceRegistry::new_empty(), 3);
+        assert_eq!(db.length, 0);
+
+        let len = 100;
+        let mut trie = BestDeviceRegistry::new_empty();
+        let mut ids: Vec<u64> = (0..len).collect();
+        let mut rng = thread_rng();
+        rng.shuffle(&mut ids);
+        for id in ids {
+            trie.add(new_device_with_id_path(
+                id,
+                format!("factory{}/machineA/{}", id % 7, id),
+            ));
+        }
+
+        let db = trie_to_btree(&trie, 5);
+        assert!(db.is_a_valid_btree());
+        assert_eq!(db.length, len);
+        for id in 0..len {
+            let dev = db.find(id).unwrap();
+            assert_eq!(dev.address, format!("My address is {}", id));
+            assert_eq!(dev.path, format!("factory{}/machineA/{}", id % 7, id));
+            assert_eq!(
+                trie.find(&dev.path).map(|d| d.numerical_id),
+                Some(dev.numerical_id)
+            );
+        }
+    }
+}
@@ -1,6 +1,10 @@
-use std::cmp::{min, Ord, Ordering};
-use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
-use std::iter::FromIterator;
+use std::cmp::{min, Reverse};
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::iter::{self, FromIterator};
+use std::mem;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
 
 #[derive(Clone, Debug)]
 pub struct IoTDevice {
@@ -27,66 +31,89 @@ impl PartialEq for IoTDevice {
 
 type KeyType = u64;
 
-#[derive(Eq, PartialEq, Clone, Debug)]
-enum TentativeWeight {
-    Infinite,
-    Number(u32),
-}
-
-impl Ord for TentativeWeight {
-    fn cmp(&self, other: &TentativeWeight) -> Ordering {
-        match other {
-            TentativeWeight::Infinite => match self {
-                TentativeWeight::Infinite => Ordering::Equal,
-                _ => Ordering::Less,
-            },
-            TentativeWeight::Number(o) => match self {
-                TentativeWeight::Infinite => Ordering::Greater,
-                TentativeWeight::Number(s) => s.cmp(o),
-            },
-        }
-    }
-}
-
-impl PartialOrd for TentativeWeight {
-    fn partial_cmp(&self, other: &TentativeWeight) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
 #[derive(Clone, Debug)]
 struct Edge {
     weight: u32,
     node: usize,
 }
 
-fn min_index(weights: &Vec<TentativeWeight>, nodes: &Vec<usize>) -> usize {
-    let mut min_weight = (weights[0].clone(), 0);
-    for node in nodes.iter() {
-        if let Some(n) = weights.get(*node) {
-            if n < &min_weight.0 {
-                min_weight = ((&weights[*node]).clone(), node.clone())
-            }
-        }
-    }
-    return min_weight.1;
+#[derive(Clone, Debug)]
+struct SignedEdge {
+    weight: i64,
+    node: usize,
 }
 
+// In an undirected graph every edge is stored in both endpoints' rows (self
+// loops only once), so `edges()` counts it twice
 pub struct InternetOfThings {
     adjacency_list: Vec<Vec<Edge>>,
+    // edges added by set_signed_edges, indexed like `adjacency_list`; only
+    // shortest_path_bellman_ford reads them and `edges()` doesn't count them
+    signed_list: Vec<Vec<SignedEdge>>,
     nodes: Vec<KeyType>,
+    // position of each id in `nodes`, the first one if an id repeats
+    index: HashMap<KeyType, usize>,
+    directed: bool,
 }
 
 impl InternetOfThings {
     pub fn new() -> InternetOfThings {
+        InternetOfThings::new_directed()
+    }
+
+    pub fn new_directed() -> InternetOfThings {
         InternetOfThings {
             adjacency_list: vec![],
+            signed_list: vec![],
             nodes: vec![],
+            index: HashMap::new(),
+            directed: true,
+        }
+    }
+
+    // set_edges and remove_edge keep both directions of every edge in step
+    pub fn new_undirected() -> InternetOfThings {
+        InternetOfThings {
+            directed: false,
+            ..InternetOfThings::new_directed()
+        }
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    // Adds nodes as they first show up (targets included) and appends every
+    // edge to its source's list
+    pub fn from_edges(edges: &[(KeyType, KeyType, u32)]) -> InternetOfThings {
+        let mut g = InternetOfThings::new();
+        for &(from, to, weight) in edges {
+            let from = g.get_or_add_node(from);
+            let to = g.get_or_add_node(to);
+            g.adjacency_list[from].push(Edge { weight, node: to });
         }
+        g
+    }
+
+    fn get_or_add_node(&mut self, node: KeyType) -> usize {
+        self.get_node_index(node).unwrap_or_else(|| {
+            self.nodes.push(node);
+            self.adjacency_list.push(vec![]);
+            self.signed_list.push(vec![]);
+            self.index.insert(node, self.nodes.len() - 1);
+            self.nodes.len() - 1
+        })
     }
 
     fn get_node_index(&self, node: KeyType) -> Option<usize> {
-        self.nodes.iter().position(|n| n == &node)
+        self.index.get(&node).cloned()
+    }
+
+    fn reindex(&mut self) {
+        self.index.clear();
+        for (i, n) in self.nodes.iter().enumerate() {
+            self.index.entry(*n).or_insert(i);
+        }
     }
 
     pub fn edges(&self) -> u64 {
@@ -99,9 +126,20 @@ impl InternetOfThings {
         self.nodes.len()
     }
 
+    pub fn density(&self) -> f64 {
+        let n = self.nodes.len() as f64;
+        if n < 2.0 {
+            0.0
+        } else {
+            self.edges() as f64 / (n * (n - 1.0))
+        }
+    }
+
     pub fn set_nodes(&mut self, nodes: Vec<KeyType>) {
         self.nodes = nodes;
-        self.adjacency_list = vec![vec![]; self.nodes.len()]
+        self.adjacency_list = vec![vec![]; self.nodes.len()];
+        self.signed_list = vec![vec![]; self.nodes.len()];
+        self.reindex();
     }
 
     pub fn set_edges(&mut self, from: KeyType, edges: Vec<(u32, KeyType)>) {
@@ -118,219 +156,982 @@ impl InternetOfThings {
                 }
             })
             .collect();
-        match self.nodes.iter().position(|n| n == &from) {
-            Some(i) => self.adjacency_list[i] = edges,
-            None => {
-                self.nodes.push(from);
-                self.adjacency_list.push(edges)
+        self.replace_edges(from, edges);
+    }
+
+    // Like set_edges, but for weights that may be negative. These edges live
+    // apart from the ones set_edges manages and only
+    // shortest_path_bellman_ford follows them.
+    pub fn set_signed_edges(&mut self, from: KeyType, edges: Vec<(i64, KeyType)>) {
+        let edges: Vec<SignedEdge> = edges
+            .into_iter()
+            .filter_map(|(weight, to)| {
+                self.get_node_index(to)
+                    .map(|node| SignedEdge { weight, node })
+            })
+            .collect();
+        let i = self.get_or_add_node(from);
+        let old = mem::replace(&mut self.signed_list[i], edges);
+        if !self.directed {
+            for e in old.iter().filter(|e| e.node != i) {
+                self.signed_list[e.node].retain(|back| back.node != i);
+            }
+            let mirrored: Vec<SignedEdge> = self.signed_list[i]
+                .iter()
+                .filter(|e| e.node != i)
+                .cloned()
+                .collect();
+            for e in mirrored {
+                self.signed_list[e.node].push(SignedEdge { node: i, ..e });
             }
         }
     }
 
-    pub fn shortest_path(&self, from: KeyType, to: KeyType) -> Option<(u32, Vec<KeyType>)> {
-        let mut src = None;
-        let mut dest = None;
+    fn replace_edges(&mut self, from: KeyType, edges: Vec<Edge>) {
+        let i = self.get_or_add_node(from);
+        let old = mem::replace(&mut self.adjacency_list[i], edges);
+        if !self.directed {
+            for e in old.iter().filter(|e| e.node != i) {
+                self.adjacency_list[e.node].retain(|back| back.node != i);
+            }
+            let mirrored: Vec<Edge> = self.adjacency_list[i]
+                .iter()
+                .filter(|e| e.node != i)
+                .cloned()
+                .collect();
+            for e in mirrored {
+                self.adjacency_list[e.node].push(Edge { node: i, ..e });
+            }
+        }
+    }
 
-        for (i, n) in self.nodes.iter().enumerate() {
-            if n == &from {
-                src = Some(i);
+    pub fn remove_edge(&mut self, from: KeyType, to: KeyType) -> bool {
+        match (self.get_node_index(from), self.get_node_index(to)) {
+            (Some(i), Some(j)) => {
+                let before = self.adjacency_list[i].len();
+                self.adjacency_list[i].retain(|e| e.node != j);
+                if !self.directed {
+                    self.adjacency_list[j].retain(|e| e.node != i);
+                }
+                self.adjacency_list[i].len() < before
             }
-            if n == &to {
-                dest = Some(i);
+            _ => false,
+        }
+    }
+
+    // Changes every edge from `from` to `to` (and their mirrors in an
+    // undirected graph), leaving the rest of the row alone
+    pub fn update_edge_weight(&mut self, from: KeyType, to: KeyType, weight: u32) -> bool {
+        let (i, j) = match (self.get_node_index(from), self.get_node_index(to)) {
+            (Some(i), Some(j)) => (i, j),
+            _ => return false,
+        };
+        let mut found = false;
+        for e in self.adjacency_list[i].iter_mut().filter(|e| e.node == j) {
+            e.weight = weight;
+            found = true;
+        }
+        if found && !self.directed {
+            for e in self.adjacency_list[j].iter_mut().filter(|e| e.node == i) {
+                e.weight = weight;
             }
-            if src.is_some() && dest.is_some() {
-                break;
+        }
+        found
+    }
+
+    // Edges store positions in `adjacency_list`, so every index past the
+    // removed node shifts down by one
+    pub fn remove_node(&mut self, node: KeyType) -> bool {
+        match self.get_node_index(node) {
+            Some(i) => {
+                self.nodes.remove(i);
+                self.adjacency_list.remove(i);
+                self.signed_list.remove(i);
+                self.reindex();
+                for edges in self.adjacency_list.iter_mut() {
+                    edges.retain(|e| e.node != i);
+                    for e in edges.iter_mut() {
+                        if e.node > i {
+                            e.node -= 1;
+                        }
+                    }
+                }
+                for edges in self.signed_list.iter_mut() {
+                    edges.retain(|e| e.node != i);
+                    for e in edges.iter_mut() {
+                        if e.node > i {
+                            e.node -= 1;
+                        }
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Same nodes, with a weight 1 edge wherever the original has none (no self-loops)
+    pub fn complement(&self) -> InternetOfThings {
+        let n = self.nodes.len();
+        let adjacency_list = (0..n)
+            .map(|u| {
+                let existing: HashSet<usize> =
+                    self.adjacency_list[u].iter().map(|e| e.node).collect();
+                (0..n)
+                    .filter(|v| *v != u && !existing.contains(v))
+                    .map(|v| Edge { weight: 1, node: v })
+                    .collect()
+            })
+            .collect();
+        InternetOfThings {
+            adjacency_list,
+            signed_list: vec![vec![]; n],
+            nodes: self.nodes.clone(),
+            index: self.index.clone(),
+            directed: self.directed,
+        }
+    }
+
+    pub fn out_degree(&self, node: KeyType) -> Option<usize> {
+        self.get_node_index(node)
+            .map(|i| self.adjacency_list[i].len())
+    }
+
+    pub fn in_degree(&self, node: KeyType) -> Option<usize> {
+        self.get_node_index(node).map(|i| {
+            self.adjacency_list
+                .iter()
+                .flatten()
+                .filter(|e| e.node == i)
+                .count()
+        })
+    }
+
+    pub fn max_degree_node(&self) -> Option<(KeyType, usize)> {
+        self.nodes
+            .iter()
+            .zip(self.adjacency_list.iter())
+            .map(|(n, edges)| (*n, edges.len()))
+            .min_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)))
+    }
+
+    // Mean out-degree over the node's outgoing edges
+    pub fn average_neighbor_degree(&self, node: KeyType) -> Option<f64> {
+        let edges = &self.adjacency_list[self.get_node_index(node)?];
+        if edges.is_empty() {
+            return None;
+        }
+        let total: usize = edges
+            .iter()
+            .map(|e| self.adjacency_list[e.node].len())
+            .sum();
+        Some(total as f64 / edges.len() as f64)
+    }
+
+    // Picks a neighbor with probability proportional to the edge weight, so
+    // edges with weight 0 are never taken
+    pub fn sample_neighbor(&self, node: KeyType, seed: u64) -> Option<KeyType> {
+        let edges = &self.adjacency_list[self.get_node_index(node)?];
+        let total: u64 = edges.iter().map(|e| e.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut pick = SmallRng::seed_from_u64(seed).gen_range(0, total);
+        for e in edges {
+            if pick < e.weight as u64 {
+                return Some(self.nodes[e.node]);
             }
+            pick -= e.weight as u64;
+        }
+        None
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.adjacency_list.iter().enumerate().all(|(u, edges)| {
+            edges.iter().all(|e| {
+                self.adjacency_list[e.node]
+                    .iter()
+                    .any(|back| back.node == u && back.weight == e.weight)
+            })
+        })
+    }
+
+    pub fn shortest_path(&self, from: KeyType, to: KeyType) -> Option<(u32, Vec<KeyType>)> {
+        let (src, dest) = (self.get_node_index(from)?, self.get_node_index(to)?);
+        self.shortest_route(src, dest, |_, _| true)
+            .map(|(cost, path)| (cost, path.into_iter().map(|i| self.nodes[i]).collect()))
+    }
+
+    // Yen's algorithm: each new path leaves an earlier one at some spur node and
+    // takes the cheapest way on that avoids the earlier paths' next edges
+    pub fn k_shortest_paths(
+        &self,
+        from: KeyType,
+        to: KeyType,
+        k: usize,
+    ) -> Vec<(u32, Vec<KeyType>)> {
+        let (src, dest) = match (self.get_node_index(from), self.get_node_index(to)) {
+            (Some(src), Some(dest)) => (src, dest),
+            _ => return vec![],
+        };
+
+        let mut found: Vec<(u32, Vec<usize>)> = vec![];
+        let mut candidates = BTreeSet::new();
+        if let Some(first) = self.shortest_route(src, dest, |_, _| true) {
+            candidates.insert(first);
         }
-        if src.is_some() && dest.is_some() {
-            let (src, dest) = (src.unwrap(), dest.unwrap());
 
-            let mut distance: Vec<TentativeWeight> =
-                vec![TentativeWeight::Infinite; self.nodes.len()];
-            distance[src] = TentativeWeight::Number(0);
+        while found.len() < k {
+            let next = match candidates.iter().next() {
+                Some(next) => next.clone(),
+                None => break,
+            };
+            candidates.remove(&next);
+            let last = &next.1;
 
-            let mut open: Vec<usize> = (0..self.nodes.len()).into_iter().collect();
-            let mut parent = vec![None; self.nodes.len()];
-            let mut found = false;
-            while !open.is_empty() {
-                let u = min_index(&distance, &open);
-                let u = open.remove(u);
+            for i in 0..last.len() - 1 {
+                let root = &last[..=i];
+                let blocked_edges: HashSet<(usize, usize)> = found
+                    .iter()
+                    .chain(iter::once(&next))
+                    .filter(|(_, p)| p.len() > i + 1 && &p[..=i] == root)
+                    .map(|(_, p)| (p[i], p[i + 1]))
+                    .collect();
+                let blocked_nodes: HashSet<usize> = root[..i].iter().cloned().collect();
 
-                if u == dest {
-                    found = true;
-                    break;
+                let spur = self.shortest_route(last[i], dest, |u, v| {
+                    !blocked_nodes.contains(&v) && !blocked_edges.contains(&(u, v))
+                });
+                if let Some((spur_cost, spur_path)) = spur {
+                    let cost = self.route_cost(root) + spur_cost;
+                    let path: Vec<usize> =
+                        root[..i].iter().chain(spur_path.iter()).cloned().collect();
+                    if !found.iter().any(|(_, p)| p == &path) {
+                        candidates.insert((cost, path));
+                    }
                 }
+            }
+            found.push(next);
+        }
 
-                let dist = distance[u].clone();
+        found
+            .into_iter()
+            .map(|(cost, path)| (cost, path.into_iter().map(|i| self.nodes[i]).collect()))
+            .collect()
+    }
 
-                for e in &self.adjacency_list[u] {
-                    let new_distance = match dist {
-                        TentativeWeight::Number(n) => TentativeWeight::Number(n + e.weight),
-                        _ => TentativeWeight::Infinite,
-                    };
+    // Cost of following `path` using the cheapest edge between each pair
+    fn route_cost(&self, path: &[usize]) -> u32 {
+        path.windows(2)
+            .map(|w| {
+                self.adjacency_list[w[0]]
+                    .iter()
+                    .filter(|e| e.node == w[1])
+                    .map(|e| e.weight)
+                    .min()
+                    .unwrap()
+            })
+            .sum()
+    }
 
-                    let old_distance = distance[e.node].clone();
+    // Cheapest path of node indices from `src` to `dest`, only following
+    // edges `usable` accepts
+    fn shortest_route(
+        &self,
+        src: usize,
+        dest: usize,
+        usable: impl Fn(usize, usize) -> bool,
+    ) -> Option<(u32, Vec<usize>)> {
+        let (distance, parent) = self.dijkstra_with(src, Some(dest), usable);
+        let cost = distance[dest]?;
 
-                    if new_distance < old_distance {
-                        distance[e.node] = new_distance;
-                        parent[e.node] = Some(u);
+        let mut path = vec![dest];
+        let mut p = dest;
+        while p != src {
+            p = parent[p].unwrap();
+            path.push(p);
+        }
+        path.reverse();
+        Some((cost, path))
+    }
+
+    // None if either node is unknown, `to` is unreachable, or a negative cycle
+    // can be reached from `from`
+    pub fn shortest_path_bellman_ford(
+        &self,
+        from: KeyType,
+        to: KeyType,
+    ) -> Option<(i64, Vec<KeyType>)> {
+        let (src, dest) = (self.get_node_index(from)?, self.get_node_index(to)?);
+        let mut distance: Vec<Option<i64>> = vec![None; self.nodes.len()];
+        let mut parent = vec![None; self.nodes.len()];
+        distance[src] = Some(0);
+
+        // one more round than a simple path needs; a change in that round
+        // means a negative cycle
+        for _ in 0..self.nodes.len() {
+            let mut changed = false;
+            for u in 0..self.nodes.len() {
+                if let Some(dist) = distance[u] {
+                    let edges = self.adjacency_list[u]
+                        .iter()
+                        .map(|e| (e.node, i64::from(e.weight)))
+                        .chain(self.signed_list[u].iter().map(|e| (e.node, e.weight)));
+                    for (v, weight) in edges {
+                        let new_distance = dist + weight;
+                        if !matches!(distance[v], Some(d) if d <= new_distance) {
+                            distance[v] = Some(new_distance);
+                            parent[v] = Some(u);
+                            changed = true;
+                        }
                     }
                 }
             }
-            if found {
-                let mut path = vec![];
-                let mut p = parent[dest].unwrap();
-                path.push(self.nodes[dest].clone());
+            if !changed {
+                let cost = distance[dest]?;
+                let mut path = vec![self.nodes[dest]];
+                let mut p = dest;
                 while p != src {
-                    path.push(self.nodes[p].clone());
                     p = parent[p].unwrap();
+                    path.push(self.nodes[p]);
                 }
-                path.push(self.nodes[src].clone());
-
                 path.reverse();
-                let cost = match distance[dest] {
-                    TentativeWeight::Number(n) => n,
-                    _ => 0,
-                };
-                Some((cost, path))
-            } else {
-                None
+                return Some((cost, path));
             }
-        } else {
-            None
         }
+        None
     }
 
-    pub fn connected(&self, from: KeyType, degree: usize) -> Option<HashSet<KeyType>> {
-        self.nodes.iter().position(|n| n == &from).map(|i| {
-            self.connected_r(i, degree)
+    // Every node on the cheapest route along with the cost of getting there
+    pub fn shortest_path_with_costs(
+        &self,
+        from: KeyType,
+        to: KeyType,
+    ) -> Option<Vec<(KeyType, u32)>> {
+        let (src, dest) = (self.get_node_index(from)?, self.get_node_index(to)?);
+        let (distance, parent) = self.dijkstra(src, Some(dest));
+        distance[dest]?;
+
+        let mut path = vec![];
+        let mut node = Some(dest);
+        while let Some(n) = node {
+            path.push((self.nodes[n], distance[n].unwrap()));
+            node = if n == src { None } else { parent[n] };
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    // Every node reachable from `from` with its distance, in node order
+    pub fn distances_from(&self, from: KeyType) -> Option<Vec<(KeyType, u32)>> {
+        let src = self.get_node_index(from)?;
+        let (distance, _) = self.dijkstra(src, None);
+        Some(
+            distance
                 .into_iter()
-                .map(|n| self.nodes[n].clone())
-                .collect()
-        })
+                .enumerate()
+                .filter_map(|(i, d)| d.map(|d| (self.nodes[i], d)))
+                .collect(),
+        )
     }
 
-    fn connected_r(&self, from: usize, degree: usize) -> HashSet<usize> {
-        if degree > 0 {
-            self.adjacency_list[from]
-                .iter()
-                .flat_map(|e| {
-                    let mut set = self.connected_r(e.node, degree - 1);
-                    set.insert(e.node);
-                    set
-                })
-                .collect()
-        } else {
-            HashSet::new()
-        }
+    // Distances and shortest-path-tree parents from `src`, stopping early once
+    // `dest` is settled
+    fn dijkstra(&self, src: usize, dest: Option<usize>) -> (Vec<Option<u32>>, Vec<Option<usize>>) {
+        self.dijkstra_with(src, dest, |_, _| true)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    fn dijkstra_with(
+        &self,
+        src: usize,
+        dest: Option<usize>,
+        usable: impl Fn(usize, usize) -> bool,
+    ) -> (Vec<Option<u32>>, Vec<Option<usize>>) {
+        let mut distance: Vec<Option<u32>> = vec![None; self.nodes.len()];
+        let mut parent = vec![None; self.nodes.len()];
+        distance[src] = Some(0);
 
-    fn new_device_with_id(id: u64) -> IoTDevice {
-        new_device_with_id_path(id, "")
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((0, src)));
+        while let Some(Reverse((dist, u))) = queue.pop() {
+            if distance[u] < Some(dist) {
+                continue;
+            }
+            if Some(u) == dest {
+                break;
+            }
+            for e in self.adjacency_list[u].iter().filter(|e| usable(u, e.node)) {
+                let new_distance = dist + e.weight;
+                if !matches!(distance[e.node], Some(d) if d <= new_distance) {
+                    distance[e.node] = Some(new_distance);
+                    parent[e.node] = Some(u);
+                    queue.push(Reverse((new_distance, e.node)));
+                }
+            }
+        }
+        (distance, parent)
     }
 
-    fn new_device_with_id_path(id: u64, path: impl Into<String>) -> IoTDevice {
-        IoTDevice::new(id, format!("My address is {}", id), path)
+    // An edge is critical if it is the only shortest way between its endpoints,
+    // so dropping it makes the trip longer (or impossible)
+    pub fn is_edge_critical(&self, from: KeyType, to: KeyType) -> bool {
+        match (self.get_node_index(from), self.get_node_index(to)) {
+            (Some(u), Some(v)) if self.adjacency_list[u].iter().any(|e| e.node == v) => {
+                let with = self.dijkstra(u, Some(v)).0[v];
+                let without = self.dijkstra_with(u, Some(v), |a, b| (a, b) != (u, v)).0[v];
+                without.is_none() || without > with
+            }
+            _ => false,
+        }
     }
 
-    fn build_graph(g: InternetOfThings, items: &Vec<IoTDevice>) -> InternetOfThings {
-        let mut g = g;
+    // Longest path from `from` along which every id is larger than the one
+    // before. Such a path can't revisit a node, so each node's best chain is
+    // worked out once and reused.
+    pub fn longest_increasing_reachable_chain(&self, from: KeyType) -> Vec<KeyType> {
+        let start = match self.get_node_index(from) {
+            Some(i) => i,
+            None => return vec![],
+        };
+        let mut chain_len = vec![0; self.nodes.len()];
+        let mut next = vec![None; self.nodes.len()];
+        self.increasing_chain_r(start, &mut chain_len, &mut next);
 
-        g.set_nodes(items.iter().map(|n| n.numerical_id.clone()).collect());
-        g.set_edges(
-            items[0].numerical_id.clone(),
-            vec![
-                (1, items[1].numerical_id.clone()),
-                (1, items[2].numerical_id.clone()),
-                (1, items[3].numerical_id.clone()),
-                (10, items[9].numerical_id.clone()),
-            ],
-        );
+        let mut chain = vec![];
+        let mut node = Some(start);
+        while let Some(n) = node {
+            chain.push(self.nodes[n]);
+            node = next[n];
+        }
+        chain
+    }
 
-        g.set_edges(
-            items[1].numerical_id.clone(),
-            vec![(1, items[0].numerical_id.clone())],
-        );
-        g.set_edges(
-            items[2].numerical_id.clone(),
-            vec![(1, items[0].numerical_id.clone())],
-        );
-        g.set_edges(
-            items[3].numerical_id.clone(),
-            vec![
-                (1, items[0].numerical_id.clone()),
-                (1, items[4].numerical_id.clone()),
-            ],
-        );
-        g.set_edges(
-            items[4].numerical_id.clone(),
-            vec![
-                (1, items[3].numerical_id.clone()),
-                (1, items[5].numerical_id.clone()),
-            ],
-        );
-        g.set_edges(
-            items[5].numerical_id.clone(),
-            vec![
-                (1, items[4].numerical_id.clone()),
-                (1, items[6].numerical_id.clone()),
-            ],
-        );
-        g.set_edges(
-            items[6].numerical_id.clone(),
-            vec![
-                (1, items[9].numerical_id.clone()),
-                (1, items[5].numerical_id.clone()),
-            ],
-        );
-        g.set_edges(
-            items[7].numerical_id.clone(),
-            vec![(1, items[9].numerical_id.clone())],
-        );
-        g.set_edges(
-            items[8].numerical_id.clone(),
-            vec![(1, items[9].numerical_id.clone())],
-        );
-        g.set_edges(
-            items[9].numerical_id.clone(),
-            vec![
-                (1, items[8].numerical_id.clone()),
-                (1, items[7].numerical_id.clone()),
-                (1, items[6].numerical_id.clone()),
-                (10, items[0].numerical_id.clone()),
-            ],
-        );
-        g
+    fn increasing_chain_r(
+        &self,
+        u: usize,
+        chain_len: &mut [usize],
+        next: &mut [Option<usize>],
+    ) -> usize {
+        if chain_len[u] == 0 {
+            chain_len[u] = 1;
+            for e in &self.adjacency_list[u] {
+                if self.nodes[e.node] > self.nodes[u] {
+                    let len = self.increasing_chain_r(e.node, chain_len, next) + 1;
+                    if len > chain_len[u] {
+                        chain_len[u] = len;
+                        next[u] = Some(e.node);
+                    }
+                }
+            }
+        }
+        chain_len[u]
     }
 
-    #[test]
-    fn graph_insert_edges() {
-        let len = 10;
-        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+    // Path to the farthest node reachable from `from`; None on a cycle (so for any
+    // undirected graph with edges, each is a 2-cycle) or if the length overflows u32
+    pub fn longest_path_dag(&self, from: KeyType) -> Option<(u32, Vec<KeyType>)> {
+        let src = self.get_node_index(from)?;
+        let order = self.topological_order()?;
 
-        let g = build_graph(InternetOfThings::new(), &items);
+        let mut distance: Vec<Option<u32>> = vec![None; self.nodes.len()];
+        let mut parent = vec![None; self.nodes.len()];
+        distance[src] = Some(0);
 
-        assert_eq!(g.edges(), 20);
-        assert_eq!(g.nodes(), len as usize);
-    }
+        for u in order {
+            if let Some(dist) = distance[u] {
+                for e in &self.adjacency_list[u] {
+                    let new_distance = dist.checked_add(e.weight)?;
+                    if distance[e.node] < Some(new_distance) {
+                        distance[e.node] = Some(new_distance);
+                        parent[e.node] = Some(u);
+                    }
+                }
+            }
+        }
 
-    #[test]
-    fn graph_find_shortest_path() {
-        let len = 10;
-        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+        let mut dest = src;
+        for (i, d) in distance.iter().enumerate() {
+            if d > &distance[dest] {
+                dest = i;
+            }
+        }
 
-        let g = build_graph(InternetOfThings::new(), &items);
+        let mut path = vec![self.nodes[dest]];
+        let mut p = dest;
+        while let Some(u) = parent[p] {
+            path.push(self.nodes[u]);
+            p = u;
+        }
+        path.reverse();
+        distance[dest].map(|cost| (cost, path))
+    }
 
-        assert_eq!(g.edges(), 20);
-        assert_eq!(g.nodes(), len as usize);
+    // Dependencies come first, ties go to the lowest id; None for a cyclic graph
+    pub fn topological_sort(&self) -> Option<Vec<KeyType>> {
+        self.topological_order()
+            .map(|order| order.into_iter().map(|i| self.nodes[i]).collect())
+    }
 
-        assert_eq!(
-            g.shortest_path(items[0].numerical_id, items[9].numerical_id),
+    // Kahn's algorithm over node indices, None if a cycle leaves nodes unvisited
+    fn topological_order(&self) -> Option<Vec<usize>> {
+        let mut in_degree = vec![0usize; self.nodes.len()];
+        for edges in &self.adjacency_list {
+            for e in edges {
+                in_degree[e.node] += 1;
+            }
+        }
+
+        // among the nodes that are ready, the lowest id goes first
+        let mut ready: BinaryHeap<Reverse<(KeyType, usize)>> = (0..self.nodes.len())
+            .filter(|i| in_degree[*i] == 0)
+            .map(|i| Reverse((self.nodes[i], i)))
+            .collect();
+        let mut order = vec![];
+        while let Some(Reverse((_, u))) = ready.pop() {
+            order.push(u);
+            for e in &self.adjacency_list[u] {
+                in_degree[e.node] -= 1;
+                if in_degree[e.node] == 0 {
+                    ready.push(Reverse((self.nodes[e.node], e.node)));
+                }
+            }
+        }
+
+        if order.len() == self.nodes.len() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+
+    pub fn betweenness_centrality(&self) -> HashMap<KeyType, f64> {
+        self.brandes(|_| 1)
+    }
+
+    pub fn weighted_betweenness_centrality(&self) -> HashMap<KeyType, f64> {
+        self.brandes(|e| e.weight as u64)
+    }
+
+    // Brandes' algorithm: a single-source shortest path search from every node
+    // counts the shortest paths (sigma) and their predecessors, then the
+    // dependencies are accumulated in reverse order of discovery.
+    fn brandes(&self, weight: impl Fn(&Edge) -> u64) -> HashMap<KeyType, f64> {
+        let n = self.nodes.len();
+        let mut centrality = vec![0f64; n];
+
+        for s in 0..n {
+            let mut stack = vec![];
+            let mut predecessors: Vec<Vec<usize>> = vec![vec![]; n];
+            let mut sigma = vec![0f64; n];
+            let mut distance: Vec<Option<u64>> = vec![None; n];
+            let mut settled = vec![false; n];
+            sigma[s] = 1.0;
+            distance[s] = Some(0);
+
+            let mut queue = BinaryHeap::new();
+            queue.push(Reverse((0, s)));
+            while let Some(Reverse((dist, v))) = queue.pop() {
+                if settled[v] {
+                    continue;
+                }
+                settled[v] = true;
+                stack.push(v);
+
+                for e in &self.adjacency_list[v] {
+                    let new_distance = dist + weight(e);
+                    match distance[e.node] {
+                        Some(d) if new_distance > d => {}
+                        Some(d) if new_distance == d => {
+                            sigma[e.node] += sigma[v];
+                            predecessors[e.node].push(v);
+                        }
+                        _ => {
+                            distance[e.node] = Some(new_distance);
+                            sigma[e.node] = sigma[v];
+                            predecessors[e.node] = vec![v];
+                            queue.push(Reverse((new_distance, e.node)));
+                        }
+                    }
+                }
+            }
+
+            let mut delta = vec![0f64; n];
+            while let Some(w) = stack.pop() {
+                for &v in &predecessors[w] {
+                    delta[v] += sigma[v] / sigma[w] * (1.0 + delta[w]);
+                }
+                if w != s {
+                    centrality[w] += delta[w];
+                }
+            }
+        }
+
+        self.nodes.iter().cloned().zip(centrality).collect()
+    }
+
+    pub fn bfs(&self, from: KeyType) -> Vec<KeyType> {
+        match self.get_node_index(from) {
+            Some(start) => self
+                .bfs_indices(start)
+                .into_iter()
+                .map(|i| self.nodes[i])
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn dfs(&self, from: KeyType) -> Vec<KeyType> {
+        match self.get_node_index(from) {
+            Some(start) => self
+                .dfs_indices(start)
+                .0
+                .into_iter()
+                .map(|i| self.nodes[i])
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn dfs_post(&self, from: KeyType) -> Vec<KeyType> {
+        match self.get_node_index(from) {
+            Some(start) => self
+                .dfs_indices(start)
+                .1
+                .into_iter()
+                .map(|i| self.nodes[i])
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    // Node indices in (pre-order, post-order) of a depth-first search from
+    // `start`, following edges in adjacency order
+    fn dfs_indices(&self, start: usize) -> (Vec<usize>, Vec<usize>) {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut pre = vec![];
+        let mut post = vec![];
+        // each entry is a node and the position of the next edge to follow
+        let mut stack = vec![(start, 0)];
+        visited[start] = true;
+        pre.push(start);
+
+        while let Some((u, i)) = stack.pop() {
+            match self.adjacency_list[u].get(i) {
+                Some(e) => {
+                    stack.push((u, i + 1));
+                    if !visited[e.node] {
+                        visited[e.node] = true;
+                        pre.push(e.node);
+                        stack.push((e.node, 0));
+                    }
+                }
+                None => post.push(u),
+            }
+        }
+        (pre, post)
+    }
+
+    // In a directed graph an edge and its reverse count as a cycle, in an
+    // undirected one it takes a loop through three or more nodes (or a
+    // self-loop, or parallel edges)
+    pub fn has_cycle(&self) -> bool {
+        if !self.directed {
+            return self.has_undirected_cycle();
+        }
+        let mut visited = vec![false; self.nodes.len()];
+        let mut on_stack = vec![false; self.nodes.len()];
+
+        for start in 0..self.nodes.len() {
+            if visited[start] {
+                continue;
+            }
+            let mut stack = vec![(start, 0)];
+            visited[start] = true;
+            on_stack[start] = true;
+
+            while let Some((u, i)) = stack.pop() {
+                match self.adjacency_list[u].get(i) {
+                    Some(e) => {
+                        stack.push((u, i + 1));
+                        if on_stack[e.node] {
+                            return true;
+                        }
+                        if !visited[e.node] {
+                            visited[e.node] = true;
+                            on_stack[e.node] = true;
+                            stack.push((e.node, 0));
+                        }
+                    }
+                    None => on_stack[u] = false,
+                }
+            }
+        }
+        false
+    }
+
+    fn has_undirected_cycle(&self) -> bool {
+        let mut parent: Vec<usize> = (0..self.nodes.len()).collect();
+        for (u, edges) in self.adjacency_list.iter().enumerate() {
+            // each edge is stored from both ends, only look at it once
+            for e in edges.iter().filter(|e| u <= e.node) {
+                let (a, b) = (find_root(&mut parent, u), find_root(&mut parent, e.node));
+                if a == b {
+                    return true;
+                }
+                parent[a] = b;
+            }
+        }
+        false
+    }
+
+    // A breadth-first search that stops as soon as `to` turns up
+    pub fn path_exists(&self, from: KeyType, to: KeyType) -> bool {
+        let (src, dest) = match (self.get_node_index(from), self.get_node_index(to)) {
+            (Some(src), Some(dest)) => (src, dest),
+            _ => return false,
+        };
+        let mut visited = vec![false; self.nodes.len()];
+        let mut queue = VecDeque::new();
+        visited[src] = true;
+        queue.push_back(src);
+
+        while let Some(u) = queue.pop_front() {
+            if u == dest {
+                return true;
+            }
+            for e in &self.adjacency_list[u] {
+                if !visited[e.node] {
+                    visited[e.node] = true;
+                    queue.push_back(e.node);
+                }
+            }
+        }
+        false
+    }
+
+    // Counts the nodes reachable from each node, not including the node itself
+    pub fn reachability_sizes(&self) -> HashMap<KeyType, usize> {
+        (0..self.nodes.len())
+            .map(|i| (self.nodes[i], self.bfs_indices(i).len() - 1))
+            .collect()
+    }
+
+    // Node indices in the order a breadth-first search from `start` visits them
+    fn bfs_indices(&self, start: usize) -> Vec<usize> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut order = vec![];
+        let mut queue = VecDeque::new();
+        visited[start] = true;
+        queue.push_back(start);
+
+        while let Some(u) = queue.pop_front() {
+            order.push(u);
+            for e in &self.adjacency_list[u] {
+                if !visited[e.node] {
+                    visited[e.node] = true;
+                    queue.push_back(e.node);
+                }
+            }
+        }
+        order
+    }
+
+    // Treats every edge as undirected
+    pub fn build_connectivity_index(&self) -> ConnectivityIndex {
+        let mut parent: Vec<usize> = (0..self.nodes.len()).collect();
+        let mut size = vec![1; self.nodes.len()];
+
+        for (u, edges) in self.adjacency_list.iter().enumerate() {
+            for e in edges {
+                let (a, b) = (find_root(&mut parent, u), find_root(&mut parent, e.node));
+                if a != b {
+                    let (small, large) = if size[a] < size[b] { (a, b) } else { (b, a) };
+                    parent[small] = large;
+                    size[large] += size[small];
+                }
+            }
+        }
+
+        let components = (0..self.nodes.len())
+            .map(|i| (self.nodes[i], find_root(&mut parent, i)))
+            .collect();
+        ConnectivityIndex { components }
+    }
+
+    // Direct neighbors in the order their edges were set
+    pub fn neighbors(&self, node: KeyType) -> Option<Vec<(KeyType, u32)>> {
+        self.get_node_index(node).map(|i| {
+            self.adjacency_list[i]
+                .iter()
+                .map(|e| (self.nodes[e.node], e.weight))
+                .collect()
+        })
+    }
+
+    // Ignores edge direction, an empty graph counts as connected
+    pub fn is_connected(&self) -> bool {
+        let index = self.build_connectivity_index();
+        self.nodes
+            .iter()
+            .all(|n| index.connected(self.nodes[0], *n))
+    }
+
+    pub fn connected(&self, from: KeyType, degree: usize) -> Option<HashSet<KeyType>> {
+        self.get_node_index(from).map(|i| {
+            self.connected_r(i, degree)
+                .into_iter()
+                .map(|n| self.nodes[n].clone())
+                .collect()
+        })
+    }
+
+    fn connected_r(&self, from: usize, degree: usize) -> HashSet<usize> {
+        if degree > 0 {
+            self.adjacency_list[from]
+                .iter()
+                .flat_map(|e| {
+                    let mut set = self.connected_r(e.node, degree - 1);
+                    set.insert(e.node);
+                    set
+                })
+                .collect()
+        } else {
+            HashSet::new()
+        }
+    }
+}
+
+fn find_root(parent: &mut [usize], i: usize) -> usize {
+    let mut i = i;
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+// A union-find snapshot of the graph's components; it does not follow later
+// changes to the graph.
+pub struct ConnectivityIndex {
+    components: HashMap<KeyType, usize>,
+}
+
+impl ConnectivityIndex {
+    pub fn connected(&self, a: KeyType, b: KeyType) -> bool {
+        match (self.components.get(&a), self.components.get(&b)) {
+            (Some(x), Some(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+
+    fn new_device_with_id(id: u64) -> IoTDevice {
+        new_device_with_id_path(id, "")
+    }
+
+    fn new_device_with_id_path(id: u64, path: impl Into<String>) -> IoTDevice {
+        IoTDevice::new(id, format!("My address is {}", id), path)
+    }
+
+    fn build_graph(g: InternetOfThings, items: &Vec<IoTDevice>) -> InternetOfThings {
+        let mut g = g;
+
+        g.set_nodes(items.iter().map(|n| n.numerical_id.clone()).collect());
+        g.set_edges(
+            items[0].numerical_id.clone(),
+            vec![
+                (1, items[1].numerical_id.clone()),
+                (1, items[2].numerical_id.clone()),
+                (1, items[3].numerical_id.clone()),
+                (10, items[9].numerical_id.clone()),
+            ],
+        );
+
+        g.set_edges(
+            items[1].numerical_id.clone(),
+            vec![(1, items[0].numerical_id.clone())],
+        );
+        g.set_edges(
+            items[2].numerical_id.clone(),
+            vec![(1, items[0].numerical_id.clone())],
+        );
+        g.set_edges(
+            items[3].numerical_id.clone(),
+            vec![
+                (1, items[0].numerical_id.clone()),
+                (1, items[4].numerical_id.clone()),
+            ],
+        );
+        g.set_edges(
+            items[4].numerical_id.clone(),
+            vec![
+                (1, items[3].numerical_id.clone()),
+                (1, items[5].numerical_id.clone()),
+            ],
+        );
+        g.set_edges(
+            items[5].numerical_id.clone(),
+            vec![
+                (1, items[4].numerical_id.clone()),
+                (1, items[6].numerical_id.clone()),
+            ],
+        );
+        g.set_edges(
+            items[6].numerical_id.clone(),
+            vec![
+                (1, items[9].numerical_id.clone()),
+                (1, items[5].numerical_id.clone()),
+            ],
+        );
+        g.set_edges(
+            items[7].numerical_id.clone(),
+            vec![(1, items[9].numerical_id.clone())],
+        );
+        g.set_edges(
+            items[8].numerical_id.clone(),
+            vec![(1, items[9].numerical_id.clone())],
+        );
+        g.set_edges(
+            items[9].numerical_id.clone(),
+            vec![
+                (1, items[8].numerical_id.clone()),
+                (1, items[7].numerical_id.clone()),
+                (1, items[6].numerical_id.clone()),
+                (10, items[0].numerical_id.clone()),
+            ],
+        );
+        g
+    }
+
+    #[test]
+    fn graph_insert_edges() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+
+        assert_eq!(g.edges(), 20);
+        assert_eq!(g.nodes(), len as usize);
+    }
+
+    #[test]
+    fn graph_find_shortest_path() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+
+        assert_eq!(g.edges(), 20);
+        assert_eq!(g.nodes(), len as usize);
+
+        assert_eq!(
+            g.shortest_path(items[0].numerical_id, items[9].numerical_id),
             Some((
                 5,
                 vec![
@@ -342,7 +1143,18 @@ mod tests {
                     items[9].numerical_id
                 ]
             ))
-        )
+        );
+        assert_eq!(
+            g.shortest_path(items[7].numerical_id, items[2].numerical_id),
+            Some((7, vec![7, 9, 6, 5, 4, 3, 0, 2]))
+        );
+        assert_eq!(g.shortest_path(0, 0), Some((0, vec![0])));
+        assert_eq!(g.shortest_path(0, 100), None);
+
+        let mut g = g;
+        g.set_edges(100, vec![(1, 0)]);
+        assert_eq!(g.shortest_path(0, 100), None);
+        assert_eq!(g.shortest_path(100, 1), Some((2, vec![100, 0, 1])));
     }
 
     #[test]
@@ -368,4 +1180,876 @@ mod tests {
             ))
         )
     }
+
+    #[test]
+    fn graph_longest_path_dag() {
+        let mut g = InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4, 5, 6]);
+        g.set_edges(1, vec![(3, 2), (2, 3)]);
+        g.set_edges(2, vec![(4, 4)]);
+        g.set_edges(3, vec![(1, 4), (9, 6)]);
+        g.set_edges(4, vec![(2, 5)]);
+
+        assert_eq!(g.longest_path_dag(1), Some((11, vec![1, 3, 6])));
+        assert_eq!(g.longest_path_dag(2), Some((6, vec![2, 4, 5])));
+        assert_eq!(g.longest_path_dag(6), Some((0, vec![6])));
+        assert_eq!(g.longest_path_dag(100), None);
+
+        g.set_edges(5, vec![(1, 1)]);
+        assert_eq!(g.longest_path_dag(1), None);
+
+        let g = InternetOfThings::from_edges(&[(1, 2, u32::MAX), (2, 3, 1)]);
+        assert_eq!(g.longest_path_dag(2), Some((1, vec![2, 3])));
+        assert_eq!(g.longest_path_dag(1), None);
+
+        let mut g = InternetOfThings::new_undirected();
+        g.set_nodes(vec![1, 2]);
+        g.set_edges(1, vec![(1, 2)]);
+        assert_eq!(g.longest_path_dag(1), None);
+    }
+
+    #[test]
+    fn graph_betweenness_centrality() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+
+        let hops = g.betweenness_centrality();
+        let weighted = g.weighted_betweenness_centrality();
+        assert_eq!(hops.len(), len as usize);
+        assert_eq!(weighted.len(), len as usize);
+
+        // dead ends never sit between two other nodes
+        for id in &[1, 2, 7, 8] {
+            assert_eq!(hops[id], 0.0);
+            assert_eq!(weighted[id], 0.0);
+        }
+
+        // counting hops, the weight-10 link between 0 and 9 is a shortcut, but
+        // by weight it is the most expensive route, so 4 and 5 carry the traffic
+        assert!(weighted[&4] > hops[&4]);
+        assert!(weighted[&5] > hops[&5]);
+        assert!(weighted[&0] < hops[&0]);
+        assert!(weighted[&9] < hops[&9]);
+    }
+
+    #[test]
+    fn graph_is_symmetric() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        assert!(g.is_symmetric());
+
+        // same connection, different weight
+        g.set_edges(items[7].numerical_id, vec![(2, items[9].numerical_id)]);
+        assert!(!g.is_symmetric());
+
+        // missing the way back
+        g.set_edges(items[7].numerical_id, vec![]);
+        assert!(!g.is_symmetric());
+
+        assert!(InternetOfThings::new().is_symmetric());
+    }
+
+    #[test]
+    fn graph_max_degree_node() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+        // nodes 0 and 9 both have four outgoing edges
+        assert_eq!(g.max_degree_node(), Some((items[0].numerical_id, 4)));
+
+        let mut items = items;
+        items.reverse();
+        let g = build_graph(InternetOfThings::new(), &items);
+        assert_eq!(g.max_degree_node(), Some((items[9].numerical_id, 4)));
+
+        assert_eq!(InternetOfThings::new().max_degree_node(), None);
+    }
+
+    #[test]
+    fn graph_density() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+        assert!((g.density() - 20.0 / 90.0).abs() < f64::EPSILON);
+
+        let mut g = InternetOfThings::new();
+        assert_eq!(g.density(), 0.0);
+        g.set_nodes(vec![1]);
+        assert_eq!(g.density(), 0.0);
+        g.set_nodes(vec![1, 2]);
+        g.set_edges(1, vec![(1, 2)]);
+        g.set_edges(2, vec![(1, 1)]);
+        assert_eq!(g.density(), 1.0);
+    }
+
+    #[test]
+    fn graph_connectivity_index() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        let index = g.build_connectivity_index();
+
+        let reachable: HashMap<KeyType, HashSet<KeyType>> = items
+            .iter()
+            .map(|i| {
+                (
+                    i.numerical_id,
+                    g.connected(i.numerical_id, g.nodes()).unwrap(),
+                )
+            })
+            .collect();
+
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let a = items[rng.gen_range(0, len as usize)].numerical_id;
+            let b = items[rng.gen_range(0, len as usize)].numerical_id;
+            assert_eq!(index.connected(a, b), a == b || reachable[&a].contains(&b));
+        }
+
+        g.set_edges(100, vec![]);
+        g.set_edges(101, vec![(1, 100)]);
+        let index = g.build_connectivity_index();
+        assert!(index.connected(100, 101));
+        assert!(index.connected(101, 100));
+        assert!(!index.connected(items[0].numerical_id, 100));
+        assert!(index.connected(items[0].numerical_id, items[9].numerical_id));
+        assert!(!index.connected(items[0].numerical_id, 1000));
+    }
+
+    #[test]
+    fn graph_sample_neighbor() {
+        let mut g = InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4, 5]);
+        g.set_edges(1, vec![(1, 2), (3, 3), (6, 4), (0, 5)]);
+        g.set_edges(2, vec![(0, 1)]);
+
+        assert_eq!(g.sample_neighbor(1, 42), g.sample_neighbor(1, 42));
+        assert_eq!(g.sample_neighbor(2, 42), None);
+        assert_eq!(g.sample_neighbor(3, 42), None);
+        assert_eq!(g.sample_neighbor(100, 42), None);
+
+        let runs = 10_000;
+        let mut counts: HashMap<KeyType, usize> = HashMap::new();
+        for seed in 0..runs {
+            *counts
+                .entry(g.sample_neighbor(1, seed).unwrap())
+                .or_insert(0) += 1;
+        }
+        let share = |n| *counts.get(&n).unwrap_or(&0) as f64 / runs as f64;
+        assert!((share(2) - 0.1).abs() < 0.03);
+        assert!((share(3) - 0.3).abs() < 0.03);
+        assert!((share(4) - 0.6).abs() < 0.03);
+        assert_eq!(share(5), 0.0);
+    }
+
+    #[test]
+    fn graph_reachability_sizes() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+        let sizes = g.reachability_sizes();
+        assert_eq!(sizes.len(), len as usize);
+        assert!(sizes.values().all(|s| *s == len as usize - 1));
+
+        // a hub feeding two chains, with a sink at the end of one of them
+        let mut g = InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4, 5, 6]);
+        g.set_edges(1, vec![(1, 2), (1, 4)]);
+        g.set_edges(2, vec![(1, 3)]);
+        g.set_edges(4, vec![(1, 5)]);
+        g.set_edges(5, vec![(1, 4)]);
+        let sizes = g.reachability_sizes();
+        assert_eq!(sizes[&1], 4);
+        assert_eq!(sizes[&2], 1);
+        assert_eq!(sizes[&3], 0);
+        assert_eq!(sizes[&4], 1);
+        assert_eq!(sizes[&5], 1);
+        assert_eq!(sizes[&6], 0);
+
+        assert!(InternetOfThings::new().reachability_sizes().is_empty());
+    }
+
+    #[test]
+    fn graph_is_edge_critical() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+        let id = |i: usize| items[i].numerical_id;
+
+        // 0 -> 3 -> 4 -> 5 -> 6 -> 9 costs 5, the direct link costs 10
+        assert!(!g.is_edge_critical(id(0), id(9)));
+        assert!(!g.is_edge_critical(id(9), id(0)));
+        assert!(g.is_edge_critical(id(0), id(1)));
+        assert!(g.is_edge_critical(id(3), id(4)));
+        assert!(g.is_edge_critical(id(6), id(9)));
+        assert!(!g.is_edge_critical(id(1), id(2)));
+        assert!(!g.is_edge_critical(id(0), 100));
+
+        // two routes of the same cost, neither edge is needed
+        let mut g = InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3]);
+        g.set_edges(1, vec![(2, 2), (1, 3)]);
+        g.set_edges(3, vec![(1, 2)]);
+        assert!(!g.is_edge_critical(1, 2));
+        assert!(g.is_edge_critical(1, 3));
+    }
+
+    #[test]
+    fn graph_from_edges() {
+        let g = InternetOfThings::from_edges(&[]);
+        assert_eq!(g.nodes(), 0);
+        assert_eq!(g.edges(), 0);
+
+        let g = InternetOfThings::from_edges(&[
+            (1, 2, 1),
+            (2, 1, 1),
+            (1, 3, 5),
+            (3, 4, 2),
+            (1, 2, 3),
+            (5, 5, 1),
+        ]);
+        assert_eq!(g.nodes(), 5);
+        assert_eq!(g.edges(), 6);
+        assert_eq!(g.shortest_path(1, 4), Some((7, vec![1, 3, 4])));
+        assert_eq!(g.max_degree_node(), Some((1, 3)));
+    }
+
+    #[test]
+    fn graph_complement() {
+        let triangle = InternetOfThings::from_edges(&[
+            (1, 2, 1),
+            (2, 1, 1),
+            (2, 3, 1),
+            (3, 2, 1),
+            (1, 3, 1),
+            (3, 1, 1),
+        ]);
+        let c = triangle.complement();
+        assert_eq!(c.nodes(), 3);
+        assert_eq!(c.edges(), 0);
+
+        let mut g = InternetOfThings::new();
+        g.set_nodes(vec![1, 2, 3, 4]);
+        let c = g.complement();
+        assert_eq!(c.nodes(), 4);
+        assert_eq!(c.edges(), 12);
+        assert_eq!(c.density(), 1.0);
+        assert!(c.is_symmetric());
+        assert_eq!(c.complement().edges(), 0);
+
+        let path = InternetOfThings::from_edges(&[(1, 2, 5), (2, 3, 5), (3, 3, 1)]);
+        let c = path.complement();
+        assert_eq!(c.edges(), 4);
+        // 1 -> 3 is the only edge left for 1, 2 -> 1 the only one for 2
+        assert_eq!(c.sample_neighbor(1, 42), Some(3));
+        assert_eq!(c.sample_neighbor(2, 42), Some(1));
+    }
+
+    #[test]
+    fn graph_shortest_path_with_costs() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+        let id = |i: usize| items[i].numerical_id;
+
+        let path = g.shortest_path_with_costs(id(0), id(9)).unwrap();
+        assert_eq!(
+            path,
+            vec![
+                (id(0), 0),
+                (id(3), 1),
+                (id(4), 2),
+                (id(5), 3),
+                (id(6), 4),
+                (id(9), 5)
+            ]
+        );
+        assert!(path.windows(2).all(|w| w[0].1 <= w[1].1));
+        let (total, nodes) = g.shortest_path(id(0), id(9)).unwrap();
+        assert_eq!(path.last().unwrap().1, total);
+        assert_eq!(path.iter().map(|p| p.0).collect::<Vec<KeyType>>(), nodes);
+
+        let path = g.shortest_path_with_costs(id(7), id(2)).unwrap();
+        assert!(path.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(path.last(), Some(&(id(2), 7)));
+
+        assert_eq!(
+            g.shortest_path_with_costs(id(4), id(4)),
+            Some(vec![(id(4), 0)])
+        );
+        assert_eq!(g.shortest_path_with_costs(id(4), 100), None);
+
+        let g = InternetOfThings::from_edges(&[(1, 2, 3), (3, 1, 1)]);
+        assert_eq!(g.shortest_path_with_costs(1, 3), None);
+    }
+
+    #[test]
+    fn graph_average_neighbor_degree() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+        let id = |i: usize| items[i].numerical_id;
+
+        // 1 only hangs off the hub 0, which itself mostly links to leaves
+        assert_eq!(g.average_neighbor_degree(id(1)), Some(4.0));
+        assert_eq!(g.average_neighbor_degree(id(0)), Some(2.0));
+        assert!(g.average_neighbor_degree(id(1)) > g.average_neighbor_degree(id(0)));
+        assert_eq!(g.average_neighbor_degree(id(4)), Some(2.0));
+        assert_eq!(g.average_neighbor_degree(100), None);
+
+        let g = InternetOfThings::from_edges(&[(1, 2, 1)]);
+        assert_eq!(g.average_neighbor_degree(1), Some(0.0));
+        assert_eq!(g.average_neighbor_degree(2), None);
+    }
+
+    #[test]
+    fn graph_longest_increasing_reachable_chain() {
+        //  5 -> 2 -> 3 -> 4 -> 1
+        //  |    |         ^
+        //  v    v         |
+        //  6    7 -> 8    9
+        //            |    ^
+        //            +----+
+        let g = InternetOfThings::from_edges(&[
+            (5, 2, 1),
+            (5, 6, 1),
+            (2, 3, 1),
+            (2, 7, 1),
+            (3, 4, 1),
+            (4, 1, 1),
+            (7, 8, 1),
+            (8, 9, 1),
+            (9, 4, 1),
+        ]);
+
+        assert_eq!(g.longest_increasing_reachable_chain(5), vec![5, 6]);
+        assert_eq!(g.longest_increasing_reachable_chain(2), vec![2, 7, 8, 9]);
+        assert_eq!(g.longest_increasing_reachable_chain(3), vec![3, 4]);
+        assert_eq!(g.longest_increasing_reachable_chain(1), vec![1]);
+        assert!(g.longest_increasing_reachable_chain(100).is_empty());
+
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+        let g = build_graph(InternetOfThings::new(), &items);
+        assert_eq!(
+            g.longest_increasing_reachable_chain(items[0].numerical_id),
+            vec![0, 3, 4, 5, 6, 9]
+        );
+    }
+
+    #[test]
+    fn graph_bfs() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        assert_eq!(g.bfs(0), vec![0, 1, 2, 3, 9, 4, 8, 7, 6, 5]);
+        assert_eq!(g.bfs(7), vec![7, 9, 8, 6, 0, 5, 1, 2, 3, 4]);
+        assert!(g.bfs(100).is_empty());
+
+        g.set_edges(100, vec![(1, 0)]);
+        g.set_edges(101, vec![]);
+        assert_eq!(g.bfs(0).len(), len as usize);
+        assert_eq!(g.bfs(100).len(), len as usize + 1);
+        assert_eq!(g.bfs(101), vec![101]);
+    }
+
+    #[test]
+    fn graph_dfs() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        assert_eq!(g.dfs(0), vec![0, 1, 2, 3, 4, 5, 6, 9, 8, 7]);
+        assert_eq!(g.dfs_post(0), vec![1, 2, 8, 7, 9, 6, 5, 4, 3, 0]);
+        assert_eq!(g.dfs(7), vec![7, 9, 8, 6, 5, 4, 3, 0, 1, 2]);
+        assert!(g.dfs(100).is_empty());
+        assert!(g.dfs_post(100).is_empty());
+
+        g.set_edges(100, vec![(1, 0)]);
+        g.set_edges(101, vec![]);
+        assert_eq!(g.dfs(100).len(), len as usize + 1);
+        assert_eq!(g.dfs_post(100).last(), Some(&100));
+        assert_eq!(g.dfs(101), vec![101]);
+        assert_eq!(g.dfs_post(101), vec![101]);
+    }
+
+    #[test]
+    fn graph_remove_edge() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        let edges = g.edges();
+
+        assert!(g.remove_edge(0, 9));
+        assert_eq!(g.edges(), edges - 1);
+        assert!(!g.remove_edge(0, 9));
+        assert_eq!(g.edges(), edges - 1);
+        // the reverse direction is a separate edge
+        assert!(g.connected(9, 1).unwrap().contains(&0));
+        assert!(!g.connected(0, 1).unwrap().contains(&9));
+
+        assert!(!g.remove_edge(1, 2));
+        assert!(!g.remove_edge(100, 0));
+        assert!(!g.remove_edge(0, 100));
+        assert_eq!(g.edges(), edges - 1);
+    }
+
+    #[test]
+    fn graph_remove_node() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        assert!(g.remove_node(5));
+        assert_eq!(g.nodes(), len as usize - 1);
+        // 5 -> 4, 5 -> 6 and the two edges pointing back at 5
+        assert_eq!(g.edges(), 16);
+        assert!(!g.remove_node(5));
+        assert_eq!(g.edges(), 16);
+
+        // 0 -> 4 never went through 5, 0 -> 9 has to fall back to the direct edge
+        assert_eq!(g.shortest_path(0, 4), Some((2, vec![0, 3, 4])));
+        assert_eq!(g.shortest_path(0, 9), Some((10, vec![0, 9])));
+        assert_eq!(g.shortest_path(9, 7), Some((1, vec![9, 7])));
+        assert_eq!(g.shortest_path(8, 6), Some((2, vec![8, 9, 6])));
+        assert_eq!(g.shortest_path(0, 6), Some((11, vec![0, 9, 6])));
+        assert_eq!(g.bfs(4), vec![4, 3, 0, 1, 2, 9, 8, 7, 6]);
+        assert!(g.bfs(5).is_empty());
+    }
+
+    #[test]
+    fn graph_has_cycle() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        assert!(!InternetOfThings::new().has_cycle());
+        assert!(build_graph(InternetOfThings::new(), &items).has_cycle());
+
+        let mut g = InternetOfThings::from_edges(&[
+            (0, 1, 1),
+            (0, 2, 1),
+            (1, 3, 1),
+            (2, 3, 1),
+            (3, 4, 1),
+            (5, 4, 1),
+        ]);
+        assert!(!g.has_cycle());
+
+        g.set_edges(4, vec![(1, 0)]);
+        assert!(g.has_cycle());
+        assert!(g.remove_edge(4, 0));
+        assert!(!g.has_cycle());
+
+        let cycle = InternetOfThings::from_edges(&[(0, 1, 1), (1, 2, 1), (2, 0, 1)]);
+        assert!(cycle.has_cycle());
+        let self_loop = InternetOfThings::from_edges(&[(0, 0, 1)]);
+        assert!(self_loop.has_cycle());
+    }
+
+    #[test]
+    fn graph_topological_sort() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        assert_eq!(InternetOfThings::new().topological_sort(), Some(vec![]));
+        assert_eq!(
+            build_graph(InternetOfThings::new(), &items).topological_sort(),
+            None
+        );
+
+        let mut g = InternetOfThings::from_edges(&[
+            (5, 4, 1),
+            (0, 2, 1),
+            (0, 1, 1),
+            (2, 3, 1),
+            (1, 3, 1),
+            (3, 4, 1),
+            (7, 6, 1),
+        ]);
+        assert_eq!(g.topological_sort(), Some(vec![0, 1, 2, 3, 5, 4, 7, 6]));
+
+        g.set_edges(4, vec![(1, 0)]);
+        assert_eq!(g.topological_sort(), None);
+    }
+
+    #[test]
+    fn graph_shortest_path_bellman_ford() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+        assert_eq!(
+            g.shortest_path_bellman_ford(0, 9),
+            Some((5, vec![0, 3, 4, 5, 6, 9]))
+        );
+        assert_eq!(g.shortest_path_bellman_ford(0, 0), Some((0, vec![0])));
+        assert_eq!(g.shortest_path_bellman_ford(0, 100), None);
+
+        let mut g = InternetOfThings::new();
+        g.set_nodes(vec![0, 1, 2, 3, 4]);
+        g.set_edges(0, vec![(4, 1), (5, 2)]);
+        g.set_edges(1, vec![(1, 3)]);
+        g.set_signed_edges(2, vec![(-3, 1)]);
+        assert_eq!(g.edges(), 3);
+        assert_eq!(
+            g.shortest_path_bellman_ford(0, 3),
+            Some((3, vec![0, 2, 1, 3]))
+        );
+        // Dijkstra never sees the debit on 2 -> 1
+        assert_eq!(g.shortest_path(0, 3), Some((5, vec![0, 1, 3])));
+        assert_eq!(g.shortest_path_bellman_ford(0, 4), None);
+
+        // a negative cycle that 0 can't reach doesn't matter
+        g.set_signed_edges(4, vec![(-1, 4)]);
+        assert_eq!(
+            g.shortest_path_bellman_ford(0, 3),
+            Some((3, vec![0, 2, 1, 3]))
+        );
+
+        g.set_signed_edges(1, vec![(1, 2)]);
+        assert_eq!(g.shortest_path_bellman_ford(0, 3), None);
+        assert_eq!(g.shortest_path_bellman_ford(3, 3), Some((0, vec![3])));
+
+        // signed edges into a removed node go with it
+        assert!(g.remove_node(2));
+        assert_eq!(g.shortest_path_bellman_ford(0, 3), Some((5, vec![0, 1, 3])));
+
+        // undirected signed edges are followed both ways
+        let mut g = InternetOfThings::new_undirected();
+        g.set_nodes(vec![0, 1, 2]);
+        g.set_signed_edges(0, vec![(2, 1)]);
+        assert_eq!(g.shortest_path_bellman_ford(1, 0), Some((2, vec![1, 0])));
+        // which makes any negative one a negative cycle
+        g.set_signed_edges(2, vec![(-1, 1)]);
+        assert_eq!(g.shortest_path_bellman_ford(0, 1), None);
+    }
+
+    #[test]
+    fn graph_large() {
+        let len = 5000;
+        let mut rng = thread_rng();
+        let mut ids: Vec<u64> = (0..len).map(|i| i * 7).collect();
+        rng.shuffle(&mut ids);
+
+        // a ring through the shuffled ids, plus a few random chords
+        let mut g = InternetOfThings::new();
+        g.set_nodes(ids.clone());
+        for (i, id) in ids.iter().enumerate() {
+            let next = ids[(i + 1) % ids.len()];
+            let chord = ids[rng.gen_range(0, ids.len())];
+            g.set_edges(*id, vec![(1, next), (len as u32, chord)]);
+        }
+        assert_eq!(g.nodes(), len as usize);
+        assert_eq!(g.edges(), 2 * len);
+
+        assert_eq!(
+            g.shortest_path(ids[0], ids[10]),
+            Some((10, ids[..=10].to_vec()))
+        );
+        assert_eq!(g.bfs(ids[42]).len(), len as usize);
+        assert!(g.shortest_path(ids[0], 1).is_none());
+
+        assert!(g.remove_node(ids[5]));
+        assert!(g.shortest_path(ids[0], ids[5]).is_none());
+        assert_eq!(
+            g.shortest_path(ids[6], ids[8]),
+            Some((2, ids[6..=8].to_vec()))
+        );
+
+        g.set_edges(1, vec![(1, ids[0])]);
+        assert_eq!(g.nodes(), len as usize);
+        assert_eq!(
+            g.shortest_path(1, ids[1]),
+            Some((2, vec![1, ids[0], ids[1]]))
+        );
+    }
+
+    #[test]
+    fn graph_degree() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        assert_eq!(g.in_degree(9), Some(4));
+        assert_eq!(g.out_degree(9), Some(4));
+        assert_eq!(g.in_degree(0), Some(4));
+        assert_eq!(g.out_degree(0), Some(4));
+        assert_eq!(g.in_degree(1), Some(1));
+        assert_eq!(g.out_degree(1), Some(1));
+        assert_eq!(g.in_degree(100), None);
+        assert_eq!(g.out_degree(100), None);
+
+        g.set_edges(100, vec![(1, 9)]);
+        assert_eq!(g.in_degree(9), Some(5));
+        assert_eq!(g.out_degree(100), Some(1));
+        assert_eq!(g.in_degree(100), Some(0));
+        assert!(g.remove_edge(0, 9));
+        assert_eq!(g.in_degree(9), Some(4));
+        assert_eq!(g.out_degree(0), Some(3));
+    }
+
+    #[test]
+    fn graph_distances_from() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        assert_eq!(
+            g.distances_from(0),
+            Some(vec![
+                (0, 0),
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (4, 2),
+                (5, 3),
+                (6, 4),
+                (7, 6),
+                (8, 6),
+                (9, 5)
+            ])
+        );
+        let distances = g.distances_from(7).unwrap();
+        for (node, d) in distances.iter() {
+            assert_eq!(g.shortest_path(7, *node).map(|p| p.0), Some(*d));
+        }
+        assert_eq!(g.distances_from(100), None);
+
+        g.set_edges(100, vec![(3, 0)]);
+        g.set_edges(101, vec![]);
+        assert_eq!(g.distances_from(0).unwrap().len(), len as usize);
+        assert_eq!(g.distances_from(100).unwrap().len(), len as usize + 1);
+        assert_eq!(g.distances_from(101), Some(vec![(101, 0)]));
+    }
+
+    #[test]
+    fn graph_k_shortest_paths() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let g = build_graph(InternetOfThings::new(), &items);
+        assert_eq!(
+            g.k_shortest_paths(0, 9, 5),
+            vec![(5, vec![0, 3, 4, 5, 6, 9]), (10, vec![0, 9])]
+        );
+        assert_eq!(
+            g.k_shortest_paths(0, 9, 1),
+            vec![(5, vec![0, 3, 4, 5, 6, 9])]
+        );
+        assert!(g.k_shortest_paths(0, 9, 0).is_empty());
+        assert!(g.k_shortest_paths(0, 100, 3).is_empty());
+        assert_eq!(g.k_shortest_paths(4, 4, 3), vec![(0, vec![4])]);
+
+        let g = InternetOfThings::from_edges(&[
+            (1, 2, 3),
+            (1, 3, 2),
+            (2, 4, 4),
+            (3, 2, 1),
+            (3, 4, 2),
+            (3, 5, 3),
+            (4, 5, 2),
+            (4, 6, 1),
+            (5, 6, 2),
+        ]);
+        let paths = g.k_shortest_paths(1, 6, 10);
+        assert_eq!(
+            paths.iter().map(|p| p.0).collect::<Vec<u32>>(),
+            vec![5, 7, 8, 8, 8, 11, 11]
+        );
+        assert_eq!(paths[0].1, vec![1, 3, 4, 6]);
+        assert_eq!(paths[1].1, vec![1, 3, 5, 6]);
+        for (cost, path) in paths.iter() {
+            let mut nodes = path.clone();
+            nodes.sort();
+            nodes.dedup();
+            assert_eq!(nodes.len(), path.len());
+            assert_eq!(
+                g.route_cost(
+                    &path
+                        .iter()
+                        .map(|n| g.get_node_index(*n).unwrap())
+                        .collect::<Vec<usize>>()
+                ),
+                *cost
+            );
+        }
+        assert_eq!(g.k_shortest_paths(1, 6, 3).len(), 3);
+    }
+
+    #[test]
+    fn graph_is_connected() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        assert!(InternetOfThings::new().is_connected());
+        let mut single = InternetOfThings::new();
+        single.set_nodes(vec![1]);
+        assert!(single.is_connected());
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        assert!(g.is_connected());
+
+        g.set_edges(100, vec![]);
+        assert!(!g.is_connected());
+        // a single edge in either direction is enough
+        g.set_edges(7, vec![(1, 9), (1, 100)]);
+        assert!(g.is_connected());
+        g.set_edges(7, vec![(1, 9)]);
+        g.set_edges(100, vec![(1, 7)]);
+        assert!(g.is_connected());
+
+        assert!(g.remove_node(9));
+        assert!(!g.is_connected());
+    }
+
+    #[test]
+    fn graph_undirected() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+        let symmetric = build_graph(InternetOfThings::new(), &items);
+        assert!(symmetric.is_directed());
+
+        let mut g = InternetOfThings::new_undirected();
+        assert!(!g.is_directed());
+        g.set_nodes((0..len).collect());
+        g.set_edges(0, vec![(1, 1), (1, 2), (1, 3), (10, 9)]);
+        g.set_edges(4, vec![(1, 3), (1, 5)]);
+        g.set_edges(6, vec![(1, 5), (1, 9)]);
+        g.set_edges(9, vec![(1, 7), (1, 8), (1, 6), (10, 0)]);
+
+        assert_eq!(g.edges(), symmetric.edges());
+        assert!(g.is_symmetric());
+        assert_eq!(g.shortest_path(0, 9), symmetric.shortest_path(0, 9));
+        assert_eq!(g.shortest_path(8, 2), symmetric.shortest_path(8, 2));
+        assert!(g.has_cycle());
+
+        // replacing a row drops the reverse of edges that are no longer there
+        g.set_edges(9, vec![(1, 7), (1, 8), (1, 6)]);
+        assert_eq!(g.edges(), 18);
+        assert!(!g.has_cycle());
+        assert_eq!(g.shortest_path(0, 9), Some((5, vec![0, 3, 4, 5, 6, 9])));
+        assert_eq!(g.shortest_path(9, 0), Some((5, vec![9, 6, 5, 4, 3, 0])));
+
+        assert!(g.remove_edge(7, 9));
+        assert_eq!(g.edges(), 16);
+        assert!(g.shortest_path(9, 7).is_none());
+        assert!(!g.remove_edge(9, 7));
+
+        g.set_edges(7, vec![(1, 7)]);
+        assert_eq!(g.edges(), 17);
+        assert!(g.has_cycle());
+
+        let mut directed = InternetOfThings::new_directed();
+        directed.set_nodes(vec![0, 1]);
+        directed.set_edges(0, vec![(1, 1)]);
+        assert_eq!(directed.edges(), 1);
+        assert!(!directed.is_symmetric());
+    }
+
+    #[test]
+    fn graph_neighbors_with_weights() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        assert_eq!(g.neighbors(0), Some(vec![(1, 1), (2, 1), (3, 1), (9, 10)]));
+        assert_eq!(g.neighbors(7), Some(vec![(9, 1)]));
+        assert_eq!(g.neighbors(100), None);
+
+        g.set_edges(100, vec![]);
+        assert_eq!(g.neighbors(100), Some(vec![]));
+    }
+
+    #[test]
+    fn graph_path_exists() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        assert!(g.path_exists(0, 7));
+        assert!(g.path_exists(7, 1));
+        assert!(g.path_exists(4, 4));
+        assert!(!g.path_exists(0, 100));
+        assert!(!g.path_exists(100, 100));
+
+        g.set_edges(100, vec![(1, 0)]);
+        assert!(g.path_exists(100, 5));
+        assert!(!g.path_exists(5, 100));
+        assert!(g.path_exists(100, 100));
+        assert!(g.remove_edge(1, 0));
+        assert!(!g.path_exists(1, 0));
+        assert!(g.path_exists(0, 1));
+    }
+
+    #[test]
+    fn graph_from_edges_slice() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+        let manual = build_graph(InternetOfThings::new(), &items);
+
+        let edges: Vec<(u64, u64, u32)> = (0..len)
+            .flat_map(|n| {
+                manual
+                    .neighbors(n)
+                    .unwrap()
+                    .into_iter()
+                    .map(move |(to, weight)| (n, to, weight))
+            })
+            .collect();
+        let g = InternetOfThings::from_edges(&edges);
+        assert_eq!(g.nodes(), manual.nodes());
+        assert_eq!(g.edges(), manual.edges());
+        for n in 0..len {
+            assert_eq!(g.neighbors(n), manual.neighbors(n));
+        }
+        assert_eq!(g.shortest_path(0, 9), manual.shortest_path(0, 9));
+
+        // 4 only ever shows up as a target
+        let g = InternetOfThings::from_edges(&[(1, 2, 1), (2, 3, 1), (3, 4, 1)]);
+        assert_eq!(g.nodes(), 4);
+        assert_eq!(g.edges(), 3);
+        assert_eq!(g.neighbors(4), Some(vec![]));
+    }
+
+    #[test]
+    fn graph_update_edge_weight() {
+        let len = 10;
+        let items: Vec<IoTDevice> = (0..len).map(new_device_with_id).collect();
+
+        let mut g = build_graph(InternetOfThings::new(), &items);
+        assert_eq!(g.shortest_path(0, 9), Some((5, vec![0, 3, 4, 5, 6, 9])));
+
+        assert!(g.update_edge_weight(0, 9, 2));
+        assert_eq!(g.shortest_path(0, 9), Some((2, vec![0, 9])));
+        assert_eq!(g.neighbors(0), Some(vec![(1, 1), (2, 1), (3, 1), (9, 2)]));
+        // the reverse edge is separate
+        assert_eq!(g.shortest_path(9, 0), Some((5, vec![9, 6, 5, 4, 3, 0])));
+        assert_eq!(g.edges(), 20);
+
+        assert!(!g.update_edge_weight(1, 2, 1));
+        assert!(!g.update_edge_weight(0, 100, 1));
+        assert!(!g.update_edge_weight(100, 0, 1));
+
+        let mut g = InternetOfThings::new_undirected();
+        g.set_nodes(vec![0, 1, 2]);
+        g.set_edges(0, vec![(1, 1), (5, 2)]);
+        g.set_edges(1, vec![(1, 0), (1, 2)]);
+        assert_eq!(g.shortest_path(2, 0), Some((2, vec![2, 1, 0])));
+        assert!(g.update_edge_weight(0, 2, 1));
+        assert_eq!(g.shortest_path(2, 0), Some((1, vec![2, 0])));
+        assert!(g.is_symmetric());
+    }
 }
@@ -1,4 +1,7 @@
 use std::boxed::Box;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::iter;
 use std::mem;
 
 #[derive(Clone, Debug)]
@@ -45,16 +48,25 @@ impl PartialEq for MessageNotification {
     }
 }
 
+#[derive(Clone)]
 pub struct MessageChecker {
     pub length: usize,
     heap: Vec<Box<MessageNotification>>,
+    ascending_id: bool,
 }
 
 impl MessageChecker {
     pub fn new_empty() -> MessageChecker {
+        MessageChecker::new_with_tiebreak(true)
+    }
+
+    // Notifications with the same number of messages come out by device id,
+    // lowest first if `ascending_id` is set
+    pub fn new_with_tiebreak(ascending_id: bool) -> MessageChecker {
         MessageChecker {
             length: 0,
             heap: vec![],
+            ascending_id,
         }
     }
 
@@ -66,7 +78,11 @@ impl MessageChecker {
     fn has_more_messages(&self, pos1: usize, pos2: usize) -> bool {
         let a = &self.heap[pos1 - 1];
         let b = &self.heap[pos2 - 1];
-        a.no_messages >= b.no_messages
+        match a.no_messages.cmp(&b.no_messages) {
+            Ordering::Equal if self.ascending_id => a.device.numerical_id < b.device.numerical_id,
+            Ordering::Equal => a.device.numerical_id > b.device.numerical_id,
+            order => order == Ordering::Greater,
+        }
     }
 
     pub fn add(&mut self, notification: MessageNotification) {
@@ -86,30 +102,82 @@ impl MessageChecker {
         if self.length > 0 {
             let elem = self.heap.swap_remove(0);
             self.length = self.heap.len();
-            let mut i = 1;
-            while i * 2 < self.length {
-                let children = (i * 2, i * 2 + 1);
-                i = if self.has_more_messages(children.0, children.1) {
-                    if self.has_more_messages(children.0, i) {
-                        self.swap(i, children.0);
-                        children.0
-                    } else {
-                        break;
-                    }
-                } else {
-                    if self.has_more_messages(children.1, i) {
-                        self.swap(i, children.1);
-                        children.1
-                    } else {
-                        break;
-                    }
-                }
-            }
+            self.sift_down(1);
             Some(*elem)
         } else {
             None
         }
     }
+
+    pub fn top_k(&self, k: usize) -> Vec<MessageNotification> {
+        self.iter_sorted().take(k).collect()
+    }
+
+    pub fn iter_sorted(&self) -> impl Iterator<Item = MessageNotification> {
+        let mut heap = self.clone();
+        iter::from_fn(move || heap.pop())
+    }
+
+    pub fn into_sorted_vec(mut self) -> Vec<MessageNotification> {
+        let mut sorted = Vec::with_capacity(self.length);
+        while let Some(n) = self.pop() {
+            sorted.push(n);
+        }
+        sorted
+    }
+
+    // Splits into (at or above threshold, below threshold), in heap order
+    pub fn partition(
+        &self,
+        threshold: u64,
+    ) -> (Vec<MessageNotification>, Vec<MessageNotification>) {
+        self.heap
+            .iter()
+            .map(|n| (**n).clone())
+            .partition(|n| n.no_messages >= threshold)
+    }
+
+    pub fn update_many(&mut self, updates: &[(u64, u64)]) {
+        let updates: HashMap<u64, u64> = updates.iter().cloned().collect();
+        for notification in self.heap.iter_mut() {
+            if let Some(no_messages) = updates.get(&notification.device.numerical_id) {
+                notification.no_messages = *no_messages;
+            }
+        }
+        self.heapify();
+    }
+
+    pub fn decay_all(&mut self, amount: u64) {
+        for notification in self.heap.iter_mut() {
+            notification.no_messages = notification.no_messages.saturating_sub(amount);
+        }
+        // saturating at zero can turn distinct counts into ties
+        self.heapify();
+    }
+
+    fn heapify(&mut self) {
+        for i in (1..=self.length / 2).rev() {
+            self.sift_down(i);
+        }
+    }
+
+    fn sift_down(&mut self, pos: usize) {
+        let mut i = pos;
+        while i * 2 <= self.length {
+            let children = (i * 2, i * 2 + 1);
+            let child =
+                if children.1 <= self.length && self.has_more_messages(children.1, children.0) {
+                    children.1
+                } else {
+                    children.0
+                };
+            if self.has_more_messages(i, child) {
+                break;
+            }
+            self.swap(i, child);
+            i = child;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -117,7 +185,6 @@ mod tests {
     use super::*;
     use rand::thread_rng;
     use rand::Rng;
-    use std::collections::HashMap;
 
     fn new_device_with_id(id: u64) -> IoTDevice {
         let mut scores = HashMap::new();
@@ -172,4 +239,143 @@ mod tests {
         assert_eq!(heap.pop(), Some(c));
         assert_eq!(heap.pop(), Some(a));
     }
+
+    #[test]
+    fn binary_heap_update_many() {
+        let mut heap = MessageChecker::new_empty();
+
+        for i in 0..6 {
+            heap.add(new_notification_with_id(i, (i + 1) * 10));
+        }
+
+        heap.update_many(&[(0, 100), (5, 1), (3, 45), (42, 1000)]);
+        assert_eq!(heap.length, 6);
+
+        let mut order = vec![];
+        while let Some(n) = heap.pop() {
+            order.push((n.device.numerical_id, n.no_messages));
+        }
+        assert_eq!(
+            order,
+            vec![(0, 100), (4, 50), (3, 45), (2, 30), (1, 20), (5, 1)]
+        );
+    }
+
+    #[test]
+    fn binary_heap_top_k() {
+        let mut heap = MessageChecker::new_empty();
+
+        let mut rng = thread_rng();
+        for i in 0..10 {
+            heap.add(new_notification_with_id(i, rng.gen_range(0, 1000)));
+        }
+
+        let mut copy = heap.clone();
+        let expected: Vec<MessageNotification> = (0..3).map(|_| copy.pop().unwrap()).collect();
+        assert_eq!(heap.top_k(3), expected);
+        assert_eq!(heap.length, 10);
+
+        let all = heap.top_k(100);
+        assert_eq!(all.len(), 10);
+        assert!(all.windows(2).all(|w| w[0].no_messages >= w[1].no_messages));
+        assert_eq!(heap.top_k(0), vec![]);
+    }
+
+    #[test]
+    fn binary_heap_iter_sorted() {
+        let mut heap = MessageChecker::new_empty();
+        assert_eq!(heap.iter_sorted().count(), 0);
+
+        let mut rng = thread_rng();
+        for i in 0..20 {
+            heap.add(new_notification_with_id(i, rng.gen_range(0, 100)));
+        }
+
+        let sorted: Vec<MessageNotification> = heap.iter_sorted().collect();
+        assert_eq!(sorted, heap.clone().into_sorted_vec());
+        assert!(sorted
+            .windows(2)
+            .all(|w| w[0].no_messages >= w[1].no_messages));
+        assert_eq!(heap.length, 20);
+        assert_eq!(heap.into_sorted_vec().len(), 20);
+    }
+
+    #[test]
+    fn binary_heap_decay_all() {
+        let mut heap = MessageChecker::new_empty();
+        heap.decay_all(10);
+        assert_eq!(heap.length, 0);
+
+        let mut rng = thread_rng();
+        for i in 0..30 {
+            heap.add(new_notification_with_id(i, rng.gen_range(0, 100)));
+        }
+        heap.decay_all(40);
+        assert_eq!(heap.length, 30);
+
+        for i in 2..=heap.length {
+            assert!(heap.heap[i / 2 - 1].no_messages >= heap.heap[i - 1].no_messages);
+        }
+        let sorted = heap.into_sorted_vec();
+        assert!(sorted.iter().all(|n| n.no_messages < 60));
+        assert!(sorted
+            .windows(2)
+            .all(|w| w[0].no_messages >= w[1].no_messages));
+    }
+
+    #[test]
+    fn binary_heap_partition() {
+        let mut heap = MessageChecker::new_empty();
+        assert_eq!(heap.partition(10), (vec![], vec![]));
+
+        let mut rng = thread_rng();
+        for i in 0..30 {
+            heap.add(new_notification_with_id(i, rng.gen_range(0, 100)));
+        }
+        heap.add(new_notification_with_id(30, 50));
+
+        let (noisy, quiet) = heap.partition(50);
+        assert_eq!(heap.length, 31);
+        assert_eq!(noisy.len() + quiet.len(), 31);
+        assert!(noisy.iter().all(|n| n.no_messages >= 50));
+        assert!(quiet.iter().all(|n| n.no_messages < 50));
+        assert!(noisy.contains(&new_notification_with_id(30, 50)));
+
+        let mut ids: Vec<u64> = noisy
+            .iter()
+            .chain(quiet.iter())
+            .map(|n| n.device.numerical_id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, (0..31).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn binary_heap_tiebreak() {
+        let ids = |heap: MessageChecker| -> Vec<u64> {
+            heap.into_sorted_vec()
+                .iter()
+                .map(|n| n.device.numerical_id)
+                .collect()
+        };
+
+        let mut ascending = MessageChecker::new_with_tiebreak(true);
+        let mut descending = MessageChecker::new_with_tiebreak(false);
+        let mut default = MessageChecker::new_empty();
+        for (id, no_messages) in [(3, 10), (1, 10), (4, 20), (2, 10), (5, 5), (0, 20)] {
+            ascending.add(new_notification_with_id(id, no_messages));
+            descending.add(new_notification_with_id(id, no_messages));
+            default.add(new_notification_with_id(id, no_messages));
+        }
+
+        assert_eq!(ids(ascending), vec![0, 4, 1, 2, 3, 5]);
+        assert_eq!(ids(descending), vec![4, 0, 3, 2, 1, 5]);
+        assert_eq!(ids(default), vec![0, 4, 1, 2, 3, 5]);
+
+        let mut heap = MessageChecker::new_with_tiebreak(false);
+        heap.add(new_notification_with_id(1, 7));
+        heap.add(new_notification_with_id(2, 7));
+        assert_eq!(heap.pop(), Some(new_notification_with_id(2, 7)));
+        assert_eq!(heap.pop(), Some(new_notification_with_id(1, 7)));
+    }
 }
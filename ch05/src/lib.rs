@@ -1,5 +1,6 @@
 mod binary_search_tree;
 mod btree;
+mod conversion;
 mod graph;
 mod heap;
 mod red_black_tree;
@@ -1,5 +1,7 @@
+use std::borrow::Cow;
 use std::boxed::Box;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::mem;
 use std::str::Chars;
 
@@ -50,9 +52,20 @@ impl PartialEq for Node {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Modified,
+}
+
 pub struct BestDeviceRegistry {
     pub length: u64,
     root: HashMap<char, Link>,
+    capacity: Option<usize>,
+    // paths from least to most recently used, only kept with a capacity
+    recency: RefCell<VecDeque<String>>,
+    case_insensitive: bool,
 }
 
 impl BestDeviceRegistry {
@@ -60,28 +73,167 @@ impl BestDeviceRegistry {
         BestDeviceRegistry {
             length: 0,
             root: HashMap::new(),
+            capacity: None,
+            recency: RefCell::new(VecDeque::new()),
+            case_insensitive: false,
+        }
+    }
+
+    // Paths are lowercased before they go into or are looked up in the trie,
+    // devices keep the path they were added with
+    pub fn new_case_insensitive() -> BestDeviceRegistry {
+        BestDeviceRegistry {
+            case_insensitive: true,
+            ..BestDeviceRegistry::new_empty()
+        }
+    }
+
+    // The path as it's laid out in the trie
+    fn trie_path<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        if self.case_insensitive {
+            Cow::Owned(path.to_lowercase())
+        } else {
+            Cow::Borrowed(path)
+        }
+    }
+
+    // Holds at most `capacity` devices, dropping the least recently added or
+    // found path to make room for a new one
+    pub fn new_lru(capacity: usize) -> BestDeviceRegistry {
+        BestDeviceRegistry {
+            capacity: Some(capacity),
+            ..BestDeviceRegistry::new_empty()
         }
     }
 
     pub fn add(&mut self, device: IoTDevice) {
-        let p = device.path.clone();
+        if device.path.is_empty() {
+            return;
+        }
+        if self.capacity.is_some() {
+            let path = device.path.clone();
+            if self.insert(device).is_none() {
+                self.length += 1;
+            }
+            self.track(&path);
+        } else {
+            self.length += 1;
+            self.insert(device);
+        }
+    }
+
+    // Collapses repeated `/` and drops a trailing one before adding, so
+    // `a//b/` and `a/b` end up on the same path. Unlike `add`, replacing an
+    // existing device doesn't count towards `length`.
+    pub fn add_normalized(&mut self, mut device: IoTDevice) {
+        let mut path = String::with_capacity(device.path.len());
+        for c in device.path.chars() {
+            if c != '/' || !path.ends_with('/') {
+                path.push(c);
+            }
+        }
+        if path.len() > 1 && path.ends_with('/') {
+            path.pop();
+        }
+        device.path = path;
+        let path = device.path.clone();
+
+        if !path.is_empty() {
+            if self.insert(device).is_none() {
+                self.length += 1;
+            }
+            self.track(&path);
+        }
+    }
+
+    // Puts the device at the end of its (non-empty) path and returns the one it replaced
+    fn insert(&mut self, device: IoTDevice) -> Option<IoTDevice> {
+        let p = self.trie_path(&device.path).into_owned();
         let mut path = p.chars();
+        let start = path.next()?;
 
-        if let Some(start) = path.next() {
-            self.length += 1;
-            let mut n = self.root.entry(start).or_insert(Node::new(start, None));
-            for c in path {
-                let tmp = n.next.entry(c).or_insert(Node::new(c, None));
-                n = tmp;
+        let mut n = self.root.entry(start).or_insert(Node::new(start, None));
+        for c in path {
+            let tmp = n.next.entry(c).or_insert(Node::new(c, None));
+            n = tmp;
+        }
+        n.value.replace(device)
+    }
+
+    // Marks `path` as the most recently used one and evicts the least
+    // recently used paths beyond the capacity
+    fn track(&mut self, path: &str) {
+        let capacity = match self.capacity {
+            Some(c) => c,
+            None => return,
+        };
+        self.touch(path);
+        while self.length > capacity as u64 {
+            let oldest = self.recency.get_mut().pop_front();
+            match oldest {
+                Some(p) => {
+                    let chars: Vec<char> = self.trie_path(&p).chars().collect();
+                    if remove_r(&mut self.root, &chars).is_some() {
+                        self.length -= 1;
+                    }
+                }
+                None => break,
             }
-            n.value = Some(device);
+        }
+    }
+
+    // A linear scan over the recency list, fine for cache-sized capacities
+    fn touch(&self, path: &str) {
+        if self.capacity.is_some() {
+            let mut recency = self.recency.borrow_mut();
+            if let Some(i) = recency.iter().position(|p| p == path) {
+                recency.remove(i);
+            }
+            recency.push_back(path.to_string());
+        }
+    }
+
+    pub fn find_or_insert_with(
+        &mut self,
+        path: &str,
+        make: impl FnOnce() -> IoTDevice,
+    ) -> IoTDevice {
+        let p = self.trie_path(path).into_owned();
+        let mut chars = p.chars();
+
+        if let Some(start) = chars.next() {
+            let mut inserted = false;
+            let device = {
+                let mut n = self.root.entry(start).or_insert(Node::new(start, None));
+                for c in chars {
+                    let tmp = n.next.entry(c).or_insert(Node::new(c, None));
+                    n = tmp;
+                }
+                n.value
+                    .get_or_insert_with(|| {
+                        inserted = true;
+                        // Stored under `path`, whatever path `make` came up with
+                        let mut device = make();
+                        device.path = path.to_string();
+                        device
+                    })
+                    .clone()
+            };
+            if inserted {
+                self.length += 1;
+            }
+            self.track(path);
+            device
+        } else {
+            make()
         }
     }
 
     pub fn find(&self, path: &str) -> Option<IoTDevice> {
+        let path = self.trie_path(path);
         let mut path = path.chars();
 
-        if let Some(start) = path.next() {
+        let found = if let Some(start) = path.next() {
             self.root.get(&start).map_or(None, |mut n| {
                 for c in path {
                     match n.next.get(&c) {
@@ -93,9 +245,225 @@ impl BestDeviceRegistry {
             })
         } else {
             None
+        };
+        if let Some(ref dev) = found {
+            self.touch(&dev.path);
+        }
+        found
+    }
+
+    // Prunes the nodes that only led to the removed device
+    pub fn remove(&mut self, path: &str) -> Option<IoTDevice> {
+        let chars: Vec<char> = self.trie_path(path).chars().collect();
+        let removed = remove_r(&mut self.root, &chars)?;
+        self.length -= 1;
+        let recency = self.recency.get_mut();
+        if let Some(i) = recency.iter().position(|p| p == &removed.path) {
+            recency.remove(i);
+        }
+        Some(removed)
+    }
+
+    // Only an exact path counts: a node on the way to other devices, or a
+    // path running past a stored one, gives back `default`
+    pub fn find_or(&self, path: &str, default: IoTDevice) -> IoTDevice {
+        match self.get_node(path).and_then(|n| n.value.as_ref()) {
+            Some(dev) => {
+                self.touch(&dev.path);
+                dev.clone()
+            }
+            None => default,
+        }
+    }
+
+    pub fn contains_path(&self, path: &str) -> bool {
+        matches!(self.get_node(path), Some(n) if n.value.is_some())
+    }
+
+    // True if a stored path runs through `prefix`, whether or not it ends there
+    pub fn has_prefix(&self, prefix: &str) -> bool {
+        if prefix.is_empty() {
+            !self.root.is_empty()
+        } else {
+            self.get_node(prefix).is_some()
+        }
+    }
+
+    // Empty nodes are pruned on removal, so reaching the prefix node is
+    // enough to know a device sits at or below it
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        self.has_prefix(prefix)
+    }
+
+    // Every device whose path starts with `prefix`, including one stored
+    // right at the prefix
+    pub fn find_by_prefix(&self, prefix: &str) -> Vec<IoTDevice> {
+        let mut devices = vec![];
+        for n in self.prefix_roots(prefix) {
+            collect_r(n, &mut devices);
+        }
+        devices
+    }
+
+    pub fn count_prefix(&self, prefix: &str) -> usize {
+        self.prefix_roots(prefix).into_iter().map(count_r).sum()
+    }
+
+    // Follows the trie while it doesn't branch; a stored path ends the common
+    // prefix too, since it's a prefix of everything below it
+    pub fn longest_common_prefix(&self) -> String {
+        let mut prefix = String::new();
+        let mut next = &self.root;
+        while next.len() == 1 {
+            let n = next.values().next().unwrap();
+            prefix.push(n.key);
+            if n.value.is_some() {
+                break;
+            }
+            next = &n.next;
+        }
+        prefix
+    }
+
+    // Every allocated node, one per character along each distinct branch
+    pub fn node_count(&self) -> usize {
+        self.root.values().map(node_count_r).sum()
+    }
+
+    // The nodes whose subtrees hold the paths starting with `prefix`
+    fn prefix_roots(&self, prefix: &str) -> Vec<&Link> {
+        if prefix.is_empty() {
+            self.root.values().collect()
+        } else {
+            self.get_node(prefix).into_iter().collect()
+        }
+    }
+
+    fn get_node(&self, path: &str) -> Option<&Link> {
+        let path = self.trie_path(path);
+        let mut path = path.chars();
+        let mut n = self.root.get(&path.next()?)?;
+        for c in path {
+            n = n.next.get(&c)?;
+        }
+        Some(n)
+    }
+
+    pub fn glob(&self, pattern: &str) -> Vec<IoTDevice> {
+        let pattern: Vec<char> = self.trie_path(pattern).chars().collect();
+        let start = glob_closure(&pattern, vec![0]);
+        let mut devices = vec![];
+        for r in self.root.values() {
+            self.glob_r(r, &pattern, &start, &mut devices);
+        }
+        devices
+    }
+
+    fn glob_r(
+        &self,
+        node: &Link,
+        pattern: &[char],
+        states: &[usize],
+        devices: &mut Vec<IoTDevice>,
+    ) {
+        // advance every pattern position that can consume this node's key;
+        // a `*` may consume it and stay put, so it branches into both cases
+        let next: Vec<usize> = states
+            .iter()
+            .filter_map(|&i| match pattern.get(i) {
+                Some('*') => Some(i),
+                Some('?') => Some(i + 1),
+                Some(c) if *c == node.key => Some(i + 1),
+                _ => None,
+            })
+            .collect();
+        let next = glob_closure(pattern, next);
+        if next.is_empty() {
+            return;
+        }
+
+        if next.contains(&pattern.len()) {
+            if let Some(ref dev) = node.value {
+                devices.push(dev.clone());
+            }
+        }
+        for n in node.next.values() {
+            self.glob_r(n, pattern, &next, devices);
+        }
+    }
+
+    // Like `glob` without `*`: each `?` stands for exactly one character, so
+    // only paths as long as the pattern can match
+    pub fn find_wildcard(&self, pattern: &str) -> Vec<IoTDevice> {
+        let pattern: Vec<char> = self.trie_path(pattern).chars().collect();
+        let mut devices = vec![];
+        if !pattern.is_empty() {
+            wildcard_r(&self.root, &pattern, &mut devices);
+        }
+        devices
+    }
+
+    // A prefix trie can't index suffixes, so this rebuilds every path
+    // while visiting all nodes: linear in the size of the trie.
+    pub fn ends_with(&self, suffix: &str) -> Vec<IoTDevice> {
+        let suffix = self.trie_path(suffix);
+        let mut devices = vec![];
+        let mut path = String::new();
+        for r in self.root.values() {
+            self.ends_with_r(r, &suffix, &mut path, &mut devices);
+        }
+        devices
+    }
+
+    fn ends_with_r(
+        &self,
+        node: &Link,
+        suffix: &str,
+        path: &mut String,
+        devices: &mut Vec<IoTDevice>,
+    ) {
+        path.push(node.key);
+        if let Some(ref dev) = node.value {
+            if path.ends_with(suffix) {
+                devices.push(dev.clone());
+            }
+        }
+        for n in node.next.values() {
+            self.ends_with_r(n, suffix, path, devices);
+        }
+        path.pop();
+    }
+
+    pub fn leaves(&self) -> Vec<IoTDevice> {
+        let mut devices = vec![];
+        for r in self.root.values() {
+            self.leaves_r(r, &mut devices);
+        }
+        devices
+    }
+
+    fn leaves_r(&self, node: &Link, devices: &mut Vec<IoTDevice>) {
+        if node.next.is_empty() {
+            if let Some(ref dev) = node.value {
+                devices.push(dev.clone());
+            }
+        }
+        for n in node.next.values() {
+            self.leaves_r(n, devices);
         }
     }
 
+    // Reports every path whose device differs in `other`, along with the
+    // device `other` has there (None for removed paths)
+    pub fn diff(
+        &self,
+        other: &BestDeviceRegistry,
+        mut on_change: impl FnMut(DiffKind, &str, Option<&IoTDevice>),
+    ) {
+        let mut path = String::new();
+        diff_r(&self.root, &other.root, &mut path, &mut on_change);
+    }
+
     pub fn walk(&self, callback: impl Fn(&IoTDevice) -> ()) {
         for r in self.root.values() {
             self.walk_r(&r, &callback);
@@ -110,6 +478,194 @@ impl BestDeviceRegistry {
             callback(dev);
         }
     }
+
+    // Devices in the same lexicographic path order as walk_sorted
+    pub fn iter(&self) -> BestDeviceRegistryIter<'_> {
+        let mut iter = BestDeviceRegistryIter { stack: vec![] };
+        iter.push_sorted(&self.root);
+        iter
+    }
+
+    // Visits devices in lexicographic path order: a device comes before the
+    // ones below it, and siblings go by ascending key
+    pub fn walk_sorted(&self, callback: impl Fn(&IoTDevice)) {
+        for r in sorted_nodes(&self.root) {
+            self.walk_sorted_r(r, &callback);
+        }
+    }
+
+    fn walk_sorted_r(&self, node: &Link, callback: &impl Fn(&IoTDevice)) {
+        if let Some(ref dev) = node.value {
+            callback(dev);
+        }
+        for n in sorted_nodes(&node.next) {
+            self.walk_sorted_r(n, callback);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a BestDeviceRegistry {
+    type Item = &'a IoTDevice;
+    type IntoIter = BestDeviceRegistryIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct BestDeviceRegistryIter<'a> {
+    // nodes still to visit, the smallest key on top
+    stack: Vec<&'a Link>,
+}
+
+impl<'a> BestDeviceRegistryIter<'a> {
+    fn push_sorted(&mut self, next: &'a HashMap<char, Link>) {
+        self.stack.extend(sorted_nodes(next).into_iter().rev());
+    }
+}
+
+impl<'a> Iterator for BestDeviceRegistryIter<'a> {
+    type Item = &'a IoTDevice;
+
+    fn next(&mut self) -> Option<&'a IoTDevice> {
+        while let Some(n) = self.stack.pop() {
+            self.push_sorted(&n.next);
+            if let Some(ref dev) = n.value {
+                return Some(dev);
+            }
+        }
+        None
+    }
+}
+
+// Takes the device off the end of `path` and drops the nodes that no longer
+// lead to any device
+fn remove_r(next: &mut HashMap<char, Link>, path: &[char]) -> Option<IoTDevice> {
+    let (c, rest) = path.split_first()?;
+    let node = next.get_mut(c)?;
+    let removed = if rest.is_empty() {
+        node.value.take()
+    } else {
+        remove_r(&mut node.next, rest)
+    };
+    if removed.is_some() && node.value.is_none() && node.next.is_empty() {
+        next.remove(c);
+    }
+    removed
+}
+
+fn collect_r(node: &Link, devices: &mut Vec<IoTDevice>) {
+    if let Some(ref dev) = node.value {
+        devices.push(dev.clone());
+    }
+    for n in node.next.values() {
+        collect_r(n, devices);
+    }
+}
+
+fn wildcard_r(next: &HashMap<char, Link>, pattern: &[char], devices: &mut Vec<IoTDevice>) {
+    let (c, rest) = match pattern.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let matches: Vec<&Link> = match c {
+        '?' => next.values().collect(),
+        c => next.get(c).into_iter().collect(),
+    };
+    for n in matches {
+        if rest.is_empty() {
+            if let Some(ref dev) = n.value {
+                devices.push(dev.clone());
+            }
+        } else {
+            wildcard_r(&n.next, rest, devices);
+        }
+    }
+}
+
+fn sorted_nodes(next: &HashMap<char, Link>) -> Vec<&Link> {
+    let mut nodes: Vec<&Link> = next.values().collect();
+    nodes.sort_by_key(|n| n.key);
+    nodes
+}
+
+fn node_count_r(node: &Link) -> usize {
+    1 + node.next.values().map(node_count_r).sum::<usize>()
+}
+
+fn count_r(node: &Link) -> usize {
+    let here = if node.value.is_some() { 1 } else { 0 };
+    here + node.next.values().map(count_r).sum::<usize>()
+}
+
+// Walks the nodes both tries share side by side; a subtree only one of them
+// has is reported as a whole
+fn diff_r(
+    old: &HashMap<char, Link>,
+    new: &HashMap<char, Link>,
+    path: &mut String,
+    on_change: &mut impl FnMut(DiffKind, &str, Option<&IoTDevice>),
+) {
+    for (key, a) in old {
+        path.push(*key);
+        match new.get(key) {
+            Some(b) => {
+                match (&a.value, &b.value) {
+                    (Some(_), None) => on_change(DiffKind::Removed, path, None),
+                    (None, Some(dev)) => on_change(DiffKind::Added, path, Some(dev)),
+                    (Some(x), Some(y)) if x != y => on_change(DiffKind::Modified, path, Some(y)),
+                    _ => {}
+                }
+                diff_r(&a.next, &b.next, path, on_change);
+            }
+            None => diff_all(a, DiffKind::Removed, path, on_change),
+        }
+        path.pop();
+    }
+    for (key, b) in new {
+        if !old.contains_key(key) {
+            path.push(*key);
+            diff_all(b, DiffKind::Added, path, on_change);
+            path.pop();
+        }
+    }
+}
+
+fn diff_all(
+    node: &Link,
+    kind: DiffKind,
+    path: &mut String,
+    on_change: &mut impl FnMut(DiffKind, &str, Option<&IoTDevice>),
+) {
+    if let Some(ref dev) = node.value {
+        match kind {
+            DiffKind::Removed => on_change(kind, path, None),
+            _ => on_change(kind, path, Some(dev)),
+        }
+    }
+    for (key, n) in &node.next {
+        path.push(*key);
+        diff_all(n, kind, path, on_change);
+        path.pop();
+    }
+}
+
+// A `*` can match the empty string, so any position sitting on one also
+// reaches the position after it.
+fn glob_closure(pattern: &[char], states: Vec<usize>) -> Vec<usize> {
+    let mut closure = vec![];
+    for mut i in states {
+        loop {
+            if !closure.contains(&i) {
+                closure.push(i);
+            }
+            match pattern.get(i) {
+                Some('*') => i += 1,
+                _ => break,
+            }
+        }
+    }
+    closure
 }
 
 #[cfg(test)]
@@ -191,4 +747,622 @@ mod tests {
         assert_eq!(trie.length, len);
         assert_eq!(trie.find("100"), None);
     }
+
+    #[test]
+    fn trie_glob() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        let len = 12;
+
+        for i in 0..len {
+            trie.add(new_device_with_id_path(
+                i,
+                format!("factory{}/machineA/{}", i % 4, i),
+            ));
+        }
+        assert_eq!(trie.length, len);
+
+        let ids = |pattern: &str| {
+            let mut ids: Vec<u64> = trie
+                .glob(pattern)
+                .into_iter()
+                .map(|d| d.numerical_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids("factory*/machineA/?"), (0..10).collect::<Vec<u64>>());
+        assert_eq!(ids("factory1/machineA/*"), vec![1, 5, 9]);
+        assert_eq!(ids("*/1?"), vec![10, 11]);
+        assert_eq!(ids("f*y*/*/1*"), vec![1, 10, 11]);
+        assert_eq!(ids("**/machineA/**3"), vec![3]);
+        assert!(ids("factory?/machineB/*").is_empty());
+        assert_eq!(ids("factory0/machineA/4"), vec![4]);
+        assert_eq!(ids("*"), (0..len).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn trie_find_or_insert_with() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        trie.add(new_device_with_id_path(1, "factory1/machineA/1"));
+        assert_eq!(trie.length, 1);
+
+        let dev = trie.find_or_insert_with("factory1/machineA/1", || {
+            panic!("the device exists already")
+        });
+        assert_eq!(dev, new_device_with_id(1));
+        assert_eq!(trie.length, 1);
+
+        let dev = trie.find_or_insert_with("factory1/machineA/2", || {
+            new_device_with_id_path(2, "factory1/machineA/2")
+        });
+        assert_eq!(dev, new_device_with_id(2));
+        assert_eq!(trie.length, 2);
+        assert_eq!(
+            trie.find("factory1/machineA/2"),
+            Some(new_device_with_id(2))
+        );
+
+        let dev = trie.find_or_insert_with("factory1/machineA/2", || new_device_with_id(3));
+        assert_eq!(dev, new_device_with_id(2));
+        assert_eq!(trie.length, 2);
+
+        let dev = trie.find_or_insert_with("factory1/machineA/3", || new_device_with_id(3));
+        assert_eq!(dev.path, "factory1/machineA/3");
+        assert_eq!(trie.length, 3);
+        assert_eq!(
+            trie.remove("factory1/machineA/3").map(|d| d.path),
+            Some("factory1/machineA/3".to_owned())
+        );
+        assert_eq!(trie.length, 2);
+    }
+
+    #[test]
+    fn trie_leaves() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert_eq!(trie.leaves(), vec![]);
+
+        trie.add(new_device_with_id_path(1, "a/b"));
+        trie.add(new_device_with_id_path(2, "a/b/c"));
+        assert_eq!(trie.leaves(), vec![new_device_with_id(2)]);
+
+        trie.add(new_device_with_id_path(3, "a/d"));
+        let mut leaves: Vec<u64> = trie.leaves().iter().map(|d| d.numerical_id).collect();
+        leaves.sort();
+        assert_eq!(leaves, vec![2, 3]);
+    }
+
+    #[test]
+    fn trie_has_prefix() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert!(!trie.has_prefix(""));
+        assert!(!trie.has_prefix("factory1"));
+
+        trie.add(new_device_with_id_path(1, "factory1/machineA/1"));
+
+        assert!(trie.has_prefix(""));
+        assert!(trie.has_prefix("f"));
+        assert!(trie.has_prefix("factory1/mac"));
+        assert!(trie.has_prefix("factory1/machineA/1"));
+        assert!(!trie.has_prefix("factory1/machineA/12"));
+        assert!(!trie.has_prefix("factory2"));
+
+        assert!(!trie.contains_path("factory1/mac"));
+        assert!(!trie.contains_path(""));
+        assert!(trie.contains_path("factory1/machineA/1"));
+    }
+
+    #[test]
+    fn trie_ends_with() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert_eq!(trie.ends_with("/1"), vec![]);
+
+        let len = 12;
+        for i in 0..len {
+            trie.add(new_device_with_id_path(
+                i,
+                format!("factory{}/machineA/{}", i % 4, i),
+            ));
+        }
+        trie.add(new_device_with_id_path(20, "factory1"));
+
+        let ids = |suffix: &str| {
+            let mut ids: Vec<u64> = trie
+                .ends_with(suffix)
+                .into_iter()
+                .map(|d| d.numerical_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids("/1"), vec![1]);
+        assert_eq!(ids("1"), vec![1, 11, 20]);
+        assert_eq!(ids("/machineA/10"), vec![10]);
+        assert!(ids("/machineB/1").is_empty());
+        assert_eq!(ids("").len(), len as usize + 1);
+    }
+
+    #[test]
+    fn trie_add_normalized() {
+        let mut trie = BestDeviceRegistry::new_empty();
+
+        trie.add_normalized(new_device_with_id_path(1, "factory1//machineA/"));
+        assert_eq!(trie.length, 1);
+        assert_eq!(
+            trie.find("factory1/machineA").map(|d| d.path),
+            Some("factory1/machineA".to_string())
+        );
+        assert!(!trie.contains_path("factory1//machineA/"));
+
+        trie.add_normalized(new_device_with_id_path(2, "factory1/machineA"));
+        assert_eq!(trie.length, 1);
+        assert_eq!(trie.find("factory1/machineA"), Some(new_device_with_id(2)));
+
+        trie.add_normalized(new_device_with_id_path(3, "//factory1///machineB//"));
+        assert_eq!(trie.length, 2);
+        assert!(trie.contains_path("/factory1/machineB"));
+
+        trie.add_normalized(new_device_with_id_path(4, "//"));
+        assert_eq!(trie.length, 3);
+        assert!(trie.contains_path("/"));
+
+        trie.add_normalized(new_device_with_id_path(5, ""));
+        assert_eq!(trie.length, 3);
+    }
+
+    #[test]
+    fn trie_lru() {
+        let mut trie = BestDeviceRegistry::new_lru(3);
+        for i in 0..3 {
+            trie.add(new_device_with_id_path(
+                i,
+                format!("factory1/machineA/{}", i),
+            ));
+        }
+        assert_eq!(trie.length, 3);
+
+        assert!(trie.find("factory1/machineA/1").is_some());
+        assert!(trie.find("factory1/machineA/0").is_some());
+        assert!(trie.find("factory1/machineA/2").is_some());
+
+        trie.add(new_device_with_id_path(3, "factory1/machineA/3"));
+        assert_eq!(trie.length, 3);
+        assert_eq!(trie.find("factory1/machineA/1"), None);
+        assert!(!trie.has_prefix("factory1/machineA/1"));
+
+        // replacing a device counts as a use, but doesn't grow the cache
+        trie.add(new_device_with_id_path(10, "factory1/machineA/0"));
+        assert_eq!(trie.length, 3);
+        trie.add(new_device_with_id_path(4, "factory1/machineA/4"));
+        assert_eq!(trie.length, 3);
+        assert_eq!(trie.find("factory1/machineA/2"), None);
+        assert_eq!(
+            trie.find("factory1/machineA/0"),
+            Some(new_device_with_id(10))
+        );
+        assert!(trie.find("factory1/machineA/3").is_some());
+        assert!(trie.find("factory1/machineA/4").is_some());
+
+        // a path inserted by find_or_insert_with is used like any other
+        let mut trie = BestDeviceRegistry::new_lru(2);
+        trie.add(new_device_with_id_path(1, "a"));
+        trie.add(new_device_with_id_path(2, "b"));
+        trie.find_or_insert_with("c", || new_device_with_id_path(3, "elsewhere"));
+        trie.add(new_device_with_id_path(4, "d"));
+        trie.add(new_device_with_id_path(5, "e"));
+        assert_eq!(trie.length, 2);
+        assert!(!trie.contains_path("c"));
+        assert!(trie.contains_path("d"));
+        assert!(trie.contains_path("e"));
+
+        let mut trie = BestDeviceRegistry::new_lru(0);
+        trie.add(new_device_with_id_path(1, "a"));
+        assert_eq!(trie.length, 0);
+        assert_eq!(trie.find("a"), None);
+    }
+
+    #[test]
+    fn trie_diff() {
+        let mut old = BestDeviceRegistry::new_empty();
+        let mut new = BestDeviceRegistry::new_empty();
+        for i in 0..5 {
+            let path = format!("factory1/machineA/{}", i);
+            old.add(new_device_with_id_path(i, path.clone()));
+            new.add(new_device_with_id_path(i, path));
+        }
+
+        let mut changes = vec![];
+        old.diff(&new, |kind, path, dev| {
+            changes.push((kind, path.to_string(), dev.cloned()))
+        });
+        assert!(changes.is_empty());
+
+        new.add(new_device_with_id_path(5, "factory2/machineB/5"));
+        new.add(new_device_with_id_path(13, "factory1/machineA/3"));
+        old.add(new_device_with_id_path(6, "factory1/machineA/4/sub"));
+
+        old.diff(&new, |kind, path, dev| {
+            changes.push((kind, path.to_string(), dev.cloned()))
+        });
+        changes.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    DiffKind::Modified,
+                    "factory1/machineA/3".to_string(),
+                    Some(new_device_with_id(13))
+                ),
+                (
+                    DiffKind::Removed,
+                    "factory1/machineA/4/sub".to_string(),
+                    None
+                ),
+                (
+                    DiffKind::Added,
+                    "factory2/machineB/5".to_string(),
+                    Some(new_device_with_id(5))
+                ),
+            ]
+        );
+
+        let mut kinds = vec![];
+        new.diff(&BestDeviceRegistry::new_empty(), |kind, _, _| {
+            kinds.push(kind)
+        });
+        assert_eq!(kinds, vec![DiffKind::Removed; 6]);
+    }
+
+    #[test]
+    fn trie_remove() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert_eq!(trie.remove("factory1"), None);
+
+        trie.add(new_device_with_id_path(1, "factory1/machineA/1"));
+        trie.add(new_device_with_id_path(2, "factory1/machineA/2"));
+        trie.add(new_device_with_id_path(3, "factory1/machineB/1"));
+        trie.add(new_device_with_id_path(4, "factory1"));
+        assert_eq!(trie.length, 4);
+
+        assert_eq!(
+            trie.remove("factory1/machineA/1"),
+            Some(new_device_with_id(1))
+        );
+        assert_eq!(trie.length, 3);
+        assert_eq!(trie.find("factory1/machineA/1"), None);
+        assert!(!trie.has_prefix("factory1/machineA/1"));
+        assert_eq!(
+            trie.find("factory1/machineA/2"),
+            Some(new_device_with_id(2))
+        );
+
+        // nothing is stored at an inner node or a missing path
+        assert_eq!(trie.remove("factory1/machineA/"), None);
+        assert_eq!(trie.remove("factory1/machineA/1"), None);
+        assert_eq!(trie.remove(""), None);
+        assert_eq!(trie.length, 3);
+
+        assert_eq!(
+            trie.remove("factory1/machineB/1"),
+            Some(new_device_with_id(3))
+        );
+        assert!(!trie.has_prefix("factory1/machineB"));
+        assert!(trie.has_prefix("factory1/machine"));
+
+        // removing the device at an inner node keeps the paths below it
+        assert_eq!(trie.remove("factory1"), Some(new_device_with_id(4)));
+        assert_eq!(
+            trie.find("factory1/machineA/2"),
+            Some(new_device_with_id(2))
+        );
+        assert_eq!(
+            trie.remove("factory1/machineA/2"),
+            Some(new_device_with_id(2))
+        );
+        assert_eq!(trie.length, 0);
+        assert!(!trie.has_prefix(""));
+
+        // a removed path no longer takes up a slot in an LRU registry
+        let mut trie = BestDeviceRegistry::new_lru(2);
+        trie.add(new_device_with_id_path(1, "a"));
+        trie.add(new_device_with_id_path(2, "b"));
+        assert!(trie.remove("a").is_some());
+        trie.add(new_device_with_id_path(3, "c"));
+        assert_eq!(trie.length, 2);
+        assert!(trie.contains_path("b"));
+        assert!(trie.contains_path("c"));
+    }
+
+    #[test]
+    fn trie_find_by_prefix() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert!(trie.find_by_prefix("factory1/").is_empty());
+
+        let len = 12;
+        for i in 0..len {
+            trie.add(new_device_with_id_path(
+                i,
+                format!("factory{}/machineA/{}", i % 4, i),
+            ));
+        }
+        trie.add(new_device_with_id_path(20, "factory1/"));
+
+        let ids = |prefix: &str| {
+            let mut ids: Vec<u64> = trie
+                .find_by_prefix(prefix)
+                .into_iter()
+                .map(|d| d.numerical_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids("factory1/"), vec![1, 5, 9, 20]);
+        assert_eq!(ids("factory1/machineA/"), vec![1, 5, 9]);
+        assert_eq!(ids("factory2/machineA/1"), vec![10]);
+        assert_eq!(ids("factory3/machineA/11"), vec![11]);
+        assert!(ids("factory4").is_empty());
+        assert!(ids("factory1/machineA/12").is_empty());
+        assert_eq!(ids("").len(), len as usize + 1);
+    }
+
+    #[test]
+    fn trie_count_prefix() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert_eq!(trie.count_prefix(""), 0);
+        assert_eq!(trie.count_prefix("factory1"), 0);
+
+        let len = 12;
+        for i in 0..len {
+            trie.add(new_device_with_id_path(
+                i,
+                format!("factory{}/machineA/{}", i % 4, i),
+            ));
+        }
+        trie.add(new_device_with_id_path(20, "factory1/"));
+
+        assert_eq!(trie.count_prefix(""), trie.length as usize);
+        assert_eq!(trie.count_prefix("factory1/"), 4);
+        assert_eq!(trie.count_prefix("factory1/machineA/"), 3);
+        assert_eq!(trie.count_prefix("factory2/machineA/1"), 1);
+        assert_eq!(trie.count_prefix("factory4"), 0);
+        for prefix in ["f", "factory0", "factory3/machineA/1"] {
+            assert_eq!(trie.count_prefix(prefix), trie.find_by_prefix(prefix).len());
+        }
+    }
+
+    #[test]
+    fn trie_longest_common_prefix() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert_eq!(trie.longest_common_prefix(), "");
+
+        trie.add(new_device_with_id_path(1, "factory1/machineA/1"));
+        assert_eq!(trie.longest_common_prefix(), "factory1/machineA/1");
+
+        trie.add(new_device_with_id_path(2, "factory1/machineA/2"));
+        trie.add(new_device_with_id_path(3, "factory1/machineB/1"));
+        assert_eq!(trie.longest_common_prefix(), "factory1/machine");
+
+        trie.add(new_device_with_id_path(4, "factory1/"));
+        assert_eq!(trie.longest_common_prefix(), "factory1/");
+
+        trie.add(new_device_with_id_path(5, "factory2"));
+        assert_eq!(trie.longest_common_prefix(), "factory");
+
+        trie.add(new_device_with_id_path(6, "warehouse1"));
+        assert_eq!(trie.longest_common_prefix(), "");
+    }
+
+    #[test]
+    fn trie_find_wildcard() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert!(trie.find_wildcard("factory?").is_empty());
+
+        let len = 12;
+        for i in 0..len {
+            trie.add(new_device_with_id_path(
+                i,
+                format!("factory{}/machineA/{}", i % 4, i),
+            ));
+        }
+        trie.add(new_device_with_id_path(20, "factory1/machineA/"));
+
+        let ids = |pattern: &str| {
+            let mut ids: Vec<u64> = trie
+                .find_wildcard(pattern)
+                .into_iter()
+                .map(|d| d.numerical_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids("factory?/machineA/1"), vec![1]);
+        assert_eq!(ids("factory?/machineA/?"), (0..10).collect::<Vec<u64>>());
+        assert_eq!(ids("factory?/machineA/1?"), vec![10, 11]);
+        assert_eq!(ids("factory2/machine?/??"), vec![10]);
+        // `?` never stands for an empty string
+        assert!(ids("factory1/machineA/?").iter().all(|id| *id != 20));
+        assert!(ids("factory?/machineA").is_empty());
+        assert!(ids("?").is_empty());
+        assert!(ids("").is_empty());
+
+        for path in [
+            "factory1/machineA/5",
+            "factory1/machineA/",
+            "factory3/machineA/7",
+            "shed",
+        ] {
+            assert_eq!(trie.find_wildcard(path).into_iter().next(), trie.find(path));
+        }
+    }
+
+    #[test]
+    fn trie_case_insensitive() {
+        let mut trie = BestDeviceRegistry::new_case_insensitive();
+        trie.add(new_device_with_id_path(1, "FactoryA/MachineB/1"));
+        trie.add(new_device_with_id_path(2, "factoryA/machineB/2"));
+
+        let found = trie.find("factorya/machineb/1").unwrap();
+        assert_eq!(found, new_device_with_id(1));
+        assert_eq!(found.path, "FactoryA/MachineB/1");
+        assert_eq!(
+            trie.find("FACTORYA/MACHINEB/2"),
+            Some(new_device_with_id(2))
+        );
+        assert!(trie.contains_path("factorya/MachineB/2"));
+        assert!(trie.has_prefix("FACTORY"));
+        assert_eq!(trie.count_prefix("factoryA/"), 2);
+        assert_eq!(trie.glob("FactoryA/*/1").len(), 1);
+        assert_eq!(trie.find_wildcard("?ACTORYA/MACHINEB/?").len(), 2);
+        assert_eq!(trie.ends_with("B/2").len(), 1);
+
+        // same path in a different case replaces the device
+        trie.add_normalized(new_device_with_id_path(3, "FACTORYA/machineb/1"));
+        assert_eq!(
+            trie.find("factorya/machineb/1"),
+            Some(new_device_with_id(3))
+        );
+        assert_eq!(
+            trie.remove("FactoryA/MachineB/1"),
+            Some(new_device_with_id(3))
+        );
+        assert_eq!(trie.find("factorya/machineb/1"), None);
+
+        let mut trie = BestDeviceRegistry::new_empty();
+        trie.add(new_device_with_id_path(1, "FactoryA/MachineB/1"));
+        assert_eq!(trie.find("factorya/machineb/1"), None);
+        assert!(trie.find("FactoryA/MachineB/1").is_some());
+    }
+
+    #[test]
+    fn trie_starts_with() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert!(!trie.starts_with("factory1"));
+
+        trie.add(new_device_with_id_path(1, "factory1/machineA/1"));
+        trie.add(new_device_with_id_path(2, "factory1/machineB/2"));
+        assert!(trie.starts_with("factory1/machineA/1"));
+        assert!(trie.starts_with("factory1/machine"));
+        assert!(trie.starts_with(""));
+        assert!(!trie.starts_with("factory2"));
+        assert!(!trie.starts_with("factory1/machineA/12"));
+
+        trie.remove("factory1/machineA/1");
+        assert!(!trie.starts_with("factory1/machineA"));
+        assert!(trie.starts_with("factory1/machine"));
+    }
+
+    #[test]
+    fn trie_walk_sorted() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        let len = 30;
+
+        let mut rng = thread_rng();
+        let mut paths: Vec<String> = (0..len)
+            .map(|i| format!("factory{}/machineA/{}", rng.gen_range(0, 5), i))
+            .collect();
+        paths.push("factory1".to_string());
+        paths.push("factory1/".to_string());
+        paths.push("a".to_string());
+        for (i, p) in paths.iter().enumerate() {
+            trie.add(new_device_with_id_path(i as u64, p.clone()));
+        }
+
+        let v: RefCell<Vec<String>> = RefCell::new(vec![]);
+        trie.walk_sorted(|n| v.borrow_mut().push(n.path.clone()));
+        paths.sort();
+        assert_eq!(v.into_inner(), paths);
+
+        let v: RefCell<Vec<String>> = RefCell::new(vec![]);
+        BestDeviceRegistry::new_empty().walk_sorted(|n| v.borrow_mut().push(n.path.clone()));
+        assert!(v.into_inner().is_empty());
+    }
+
+    #[test]
+    fn trie_iter() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert_eq!(trie.iter().count(), 0);
+
+        let len = 30;
+        let mut rng = thread_rng();
+        for i in 0..len {
+            trie.add(new_device_with_id_path(
+                i,
+                format!("factory{}/machineA/{}", rng.gen_range(0, 5), i),
+            ));
+        }
+        trie.add(new_device_with_id_path(100, "factory1"));
+
+        let v: RefCell<Vec<IoTDevice>> = RefCell::new(vec![]);
+        trie.walk_sorted(|n| v.borrow_mut().push(n.clone()));
+        let walked = v.into_inner();
+        let iterated: Vec<IoTDevice> = trie.iter().cloned().collect();
+        assert_eq!(iterated, walked);
+        assert!(iterated.windows(2).all(|w| w[0].path < w[1].path));
+
+        let mut ids = vec![];
+        for dev in &trie {
+            if dev.path.starts_with("factory1") {
+                ids.push(dev.numerical_id);
+            }
+        }
+        assert_eq!(ids.first(), Some(&100));
+        assert_eq!(ids.len(), trie.count_prefix("factory1"));
+    }
+
+    #[test]
+    fn trie_node_count() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        assert_eq!(trie.node_count(), 0);
+
+        trie.add(new_device_with_id_path(1, "factory1/machineA/1"));
+        assert_eq!(trie.node_count(), 19);
+        trie.add(new_device_with_id_path(2, "factory1/machineA/2"));
+        assert_eq!(trie.node_count(), 20);
+        trie.add(new_device_with_id_path(3, "factory2"));
+        assert_eq!(trie.node_count(), 21);
+        // replacing a device or storing one at an inner node adds no nodes
+        trie.add(new_device_with_id_path(4, "factory1/machineA/1"));
+        trie.add(new_device_with_id_path(5, "factory1"));
+        assert_eq!(trie.node_count(), 21);
+        trie.add(new_device_with_id_path(6, "plant"));
+        assert_eq!(trie.node_count(), 26);
+
+        trie.remove("factory1/machineA/2");
+        assert_eq!(trie.node_count(), 25);
+        trie.remove("factory1/machineA/1");
+        assert_eq!(trie.node_count(), 14);
+    }
+
+    #[test]
+    fn trie_find_or() {
+        let mut trie = BestDeviceRegistry::new_empty();
+        let default = new_device_with_id_path(0, "unknown");
+        assert_eq!(trie.find_or("factory1", default.clone()), default);
+
+        trie.add(new_device_with_id_path(1, "factory1/machineA/1"));
+        trie.add(new_device_with_id_path(2, "factory1/machineA/"));
+
+        assert_eq!(
+            trie.find_or("factory1/machineA/1", default.clone()),
+            new_device_with_id(1)
+        );
+        assert_eq!(
+            trie.find_or("factory1/machineA/", default.clone()),
+            new_device_with_id(2)
+        );
+        // the node exists, but holds no device
+        assert_eq!(trie.find_or("factory1/mach", default.clone()), default);
+        assert_eq!(
+            trie.find_or("factory1/machineA/2", default.clone()),
+            default
+        );
+        assert_eq!(
+            trie.find_or("factory1/machineA/12", default.clone()),
+            default
+        );
+        assert_eq!(trie.find_or("", default.clone()), default);
+    }
 }